@@ -1,4 +1,5 @@
 use glob::MatchOptions;
+use std::time::Duration;
 
 // General
 
@@ -10,6 +11,25 @@ pub const PATH_MATCH_OPTIONS: MatchOptions = MatchOptions {
     require_literal_leading_dot: true, // makes linux and windows behave the same
 };
 
+// File-Support
+
+pub const GAME_EXECUTABLES: [&str; 2] =
+    ["Stronghold Crusader.exe", "Stronghold_Crusader_Extreme.exe"];
+
+pub const SCAN_DEFAULT_MAX_MATCHES: usize = 1000;
+
+pub const FILE_TEMP_SUFFIX: &str = ".ucp-tmp"; // written next to the target, then renamed over it
+pub const FILE_BACKUP_SUFFIX: &str = ".ucp-backup";
+
+// Fs-Watcher
+
+pub const FS_WATCH_EVENT: &str = "fs-watch";
+pub const FS_WATCH_DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+// Extension-Support
+
+pub const EXTENSION_DEFINITION_MAX_BYTES: u64 = 1024 * 1024; // definitions are small, larger ones are rejected unread
+
 // Gui-Config
 
 pub const NUMBER_OF_RECENT_FOLDERS: usize = 10;
@@ -30,3 +50,18 @@ pub const LOG_FILE_PATTERN: &str = "ucp-gui.{}.log";
 pub const LOG_COUNT: u32 = 25;
 pub const LOG_BYTE_SIZE: u64 = 1024 * 1024; // 1MB as max log file size to roll
 pub const LOG_BACKEND_EVENT: &str = "backend-log";
+
+// Zip-Support
+
+pub const ZIP_EXTRACT_PROGRESS_EVENT: &str = "zip-extract-progress";
+pub const ZIP_PACK_PROGRESS_EVENT: &str = "zip-pack-progress";
+
+pub const ZIP_HANDLE_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60); // unused handles are considered leaked
+pub const ZIP_HANDLE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub const ZIP_LIMIT_MAX_TOTAL_BYTES: u64 = 8 * 1024 * 1024 * 1024; // 8GB, far more than any game release
+pub const ZIP_LIMIT_MAX_ENTRIES: usize = 100_000;
+pub const ZIP_LIMIT_MAX_RATIO: u64 = 200;
+pub const ZIP_LIMIT_MAX_ENTRY_BYTES: u64 = 2 * 1024 * 1024 * 1024; // 2GB
+pub const ZIP_MEMORY_READER_MAX_BYTES: u64 = 512 * 1024 * 1024; // archives read from memory are fully held in it
+pub const ZIP_DIFF_MAX_TEXT_BYTES: u64 = 1024 * 1024; // larger text files are only compared by hash
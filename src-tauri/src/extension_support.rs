@@ -0,0 +1,208 @@
+use serde::Serialize;
+use std::{fs, path::Path};
+use tauri::AppHandle;
+
+use crate::{
+    constants::EXTENSION_DEFINITION_MAX_BYTES,
+    hash_utils::{get_sha256_of_folder, get_sha256_of_path},
+    utils::get_allowed_path_with_string_error,
+    zip_support::{archive::ArchiveSource, open_checked_archive},
+};
+
+const DEFINITION_FILE: &str = "definition.yml";
+const CONFIG_FILE: &str = "config.yml";
+const LOCALE_FOLDER: &str = "locale";
+
+/// Everything discovery needs to know about an extension before loading it.
+#[derive(Serialize)]
+pub struct ExtensionInfo {
+    pub is_zip: bool,
+
+    /// The parsed definition.yml.
+    pub definition: serde_json::Value,
+
+    pub has_config: bool,
+
+    /// Languages with a yml file in the locale folder, like "en" or "de".
+    pub locales: Vec<String>,
+
+    /// Sha256 of the zip, or of all files for folders.
+    pub hash: String,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum ExtensionInfoResult {
+    Ok { path: String, info: ExtensionInfo },
+    Error { path: String, message: String },
+}
+
+fn parse_definition(text: &str) -> Result<serde_json::Value, String> {
+    serde_yaml::from_str(text).map_err(|err| format!("definition.yml is invalid: {}", err))
+}
+
+fn get_locale_language(file_name: &str) -> Option<String> {
+    file_name
+        .strip_suffix(".yml")
+        .filter(|language| !language.is_empty() && !language.contains('/'))
+        .map(String::from)
+}
+
+// extensions are third party archives, so they go through the same checks as every read archive
+fn read_zip_extension(path: &Path) -> Result<ExtensionInfo, String> {
+    let mut archive = open_checked_archive(&ArchiveSource::File(path.to_path_buf()), None)
+        .map_err(|err| err.into_string())?;
+    let definition_index = archive
+        .find_entry(DEFINITION_FILE)
+        .ok_or(String::from("extension.definition.missing"))?;
+    if archive.entries()[definition_index].size > EXTENSION_DEFINITION_MAX_BYTES {
+        return Err(String::from("extension.definition.too.large"));
+    }
+    let mut definition_text = String::new();
+    archive
+        .read_entry(DEFINITION_FILE, |reader| {
+            reader.read_to_string(&mut definition_text)?;
+            Ok(())
+        })
+        .map_err(|err| err.to_string())?;
+
+    let locale_prefix = format!("{}/", LOCALE_FOLDER);
+    let mut locales: Vec<String> = archive
+        .entries()
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .filter_map(|entry| entry.name.strip_prefix(&locale_prefix))
+        .filter_map(get_locale_language)
+        .collect();
+    locales.sort();
+
+    Ok(ExtensionInfo {
+        is_zip: true,
+        definition: parse_definition(&definition_text)?,
+        has_config: archive.find_entry(CONFIG_FILE).is_some(),
+        locales,
+        hash: get_sha256_of_path(path).map_err(|err| err.to_string())?,
+    })
+}
+
+fn read_folder_extension(path: &Path) -> Result<ExtensionInfo, String> {
+    let definition_path = path.join(DEFINITION_FILE);
+    if !definition_path.is_file() {
+        return Err(String::from("extension.definition.missing"));
+    }
+    let definition_size = fs::metadata(&definition_path)
+        .map_err(|err| err.to_string())?
+        .len();
+    if definition_size > EXTENSION_DEFINITION_MAX_BYTES {
+        return Err(String::from("extension.definition.too.large"));
+    }
+    let definition_text = fs::read_to_string(definition_path).map_err(|err| err.to_string())?;
+
+    let mut locales = Vec::new();
+    let locale_path = path.join(LOCALE_FOLDER);
+    if locale_path.is_dir() {
+        for dir_entry in fs::read_dir(locale_path).map_err(|err| err.to_string())? {
+            let dir_entry = dir_entry.map_err(|err| err.to_string())?;
+            if !dir_entry.path().is_file() {
+                continue;
+            }
+            if let Some(language) = get_locale_language(&dir_entry.file_name().to_string_lossy()) {
+                locales.push(language);
+            }
+        }
+    }
+    locales.sort();
+
+    Ok(ExtensionInfo {
+        is_zip: false,
+        definition: parse_definition(&definition_text)?,
+        has_config: path.join(CONFIG_FILE).is_file(),
+        locales,
+        hash: get_sha256_of_folder(path).map_err(|err| err.to_string())?,
+    })
+}
+
+fn read_extension(app_handle: &AppHandle, path: &str) -> Result<ExtensionInfo, String> {
+    let extension_path = get_allowed_path_with_string_error(app_handle, path)?;
+    if extension_path.is_dir() {
+        read_folder_extension(extension_path)
+    } else {
+        read_zip_extension(extension_path)
+    }
+}
+
+// async (other thread), since reading all extensions takes a while
+// a failing extension does not stop the others, it is returned with its error
+#[tauri::command]
+pub async fn get_extension_infos(
+    app_handle: AppHandle,
+    paths: Vec<String>,
+) -> Result<Vec<ExtensionInfoResult>, String> {
+    Ok(paths
+        .into_iter()
+        .map(|path| match read_extension(&app_handle, &path) {
+            Ok(info) => ExtensionInfoResult::Ok { path, info },
+            Err(message) => ExtensionInfoResult::Error { path, message },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, io::Write};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    fn create_extension(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            // stored, so the size checks are reached before the ratio check
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn reads_zip_extension() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("extension.zip");
+        create_extension(
+            &path,
+            &[
+                ("definition.yml", b"name: test\nversion: 1.0.0\n"),
+                ("config.yml", b""),
+                ("locale/en.yml", b""),
+                ("locale/de.yml", b""),
+            ],
+        );
+        let info = read_zip_extension(&path).unwrap();
+        assert_eq!(info.definition["name"], "test");
+        assert!(info.has_config);
+        assert_eq!(info.locales, vec!["de", "en"]);
+    }
+
+    #[test]
+    fn rejects_unsafe_archives() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("extension.zip");
+        create_extension(
+            &path,
+            &[("definition.yml", b"name: test"), ("../escape.txt", b"")],
+        );
+        assert!(read_zip_extension(&path).is_err());
+    }
+
+    #[test]
+    fn rejects_large_definitions() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("extension.zip");
+        let definition = vec![b'#'; EXTENSION_DEFINITION_MAX_BYTES as usize + 1];
+        create_extension(&path, &[("definition.yml", &definition)]);
+        assert_eq!(
+            read_zip_extension(&path).err().as_deref(),
+            Some("extension.definition.too.large")
+        );
+    }
+}
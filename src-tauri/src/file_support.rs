@@ -1,45 +1,33 @@
-use path_slash::{PathBufExt, PathExt};
+use path_slash::PathExt;
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Read},
     path::Path,
 };
-use tauri::{
-    api::dir::{read_dir, DiskEntry},
-    scope::GlobPattern,
-    AppHandle, FsScope, Manager,
-};
+use tauri::{scope::GlobPattern, AppHandle, Manager};
 
 use crate::{
-    constants::PATH_MATCH_OPTIONS,
+    constants::{PATH_MATCH_OPTIONS, SCAN_DEFAULT_MAX_MATCHES},
     utils::{get_allowed_path, get_allowed_path_with_string_error},
 };
 
-fn fill_with_paths_with_slash(
-    fs_scope: &FsScope,
-    disk_entries: &Vec<DiskEntry>,
-    fill_container: &mut Vec<String>,
-) {
-    for entry in disk_entries {
-        if fs_scope.is_allowed(&entry.path) {
-            if let Some(path_string) = entry.path.to_slash() {
-                fill_container.push(path_string.to_string());
-            }
-        }
-        if let Some(children) = &entry.children {
-            fill_with_paths_with_slash(fs_scope, children, fill_container);
-        }
-    }
-}
+mod byte_scan;
+mod dir_walk;
+mod pe_info;
+pub mod safe_write;
 
-// the method will only return paths with the unix separator
-#[tauri::command]
-pub async fn read_and_filter_dir(
-    app_handle: AppHandle,
+use byte_scan::BytePattern;
+use dir_walk::{DirEntryInfo, ReadDirOptions};
+use pe_info::PeInfo;
+use safe_write::SafeWriteError;
+
+fn read_and_filter_entries(
+    app_handle: &AppHandle,
     base: &str,
     pattern: &str,
-) -> Result<Vec<String>, String> {
-    let base_path = match get_allowed_path(&app_handle, base) {
+    options: &ReadDirOptions,
+) -> Result<Vec<DirEntryInfo>, String> {
+    let base_path = match get_allowed_path(app_handle, base) {
         Ok(path) => {
             if path.exists() {
                 path
@@ -51,17 +39,42 @@ pub async fn read_and_filter_dir(
     };
     let path = dunce::canonicalize(base_path).map_err(|err| err.to_string())?;
 
-    let found_entries = read_dir(path, true).map_err(|err| err.to_string())?;
-    let mut found_paths = vec![];
-    fill_with_paths_with_slash(&app_handle.fs_scope(), &found_entries, &mut found_paths);
+    let fs_scope = app_handle.fs_scope();
+    let mut found_entries =
+        dir_walk::read_dir_entries(&path, options, |entry_path| fs_scope.is_allowed(entry_path))?;
 
     if !pattern.is_empty() {
         let glob_pattern = GlobPattern::new(pattern).map_err(|err| err.to_string())?;
-        found_paths.retain(|path_string: &String| {
-            glob_pattern.matches_with(path_string, PATH_MATCH_OPTIONS)
+        found_entries.retain(|entry: &DirEntryInfo| {
+            glob_pattern.matches_with(&entry.path, PATH_MATCH_OPTIONS)
         });
     }
-    Ok(found_paths)
+    Ok(found_entries)
+}
+
+// the method will only return paths with the unix separator
+#[tauri::command]
+pub async fn read_and_filter_dir(
+    app_handle: AppHandle,
+    base: &str,
+    pattern: &str,
+    options: Option<ReadDirOptions>,
+) -> Result<Vec<String>, String> {
+    let entries =
+        read_and_filter_entries(&app_handle, base, pattern, &options.unwrap_or_default())?;
+    Ok(entries.into_iter().map(|entry| entry.path).collect())
+}
+
+// same as "read_and_filter_dir", but every entry carries its size, modification time and type,
+// so callers do not need to request them for every file
+#[tauri::command]
+pub async fn read_and_filter_dir_entries(
+    app_handle: AppHandle,
+    base: &str,
+    pattern: &str,
+    options: Option<ReadDirOptions>,
+) -> Result<Vec<DirEntryInfo>, String> {
+    read_and_filter_entries(&app_handle, base, pattern, &options.unwrap_or_default())
 }
 
 fn slashify_path(path: &Path) -> Result<String, String> {
@@ -96,9 +109,24 @@ pub async fn canonicalize(
     }
 }
 
-// searches for a sequence of bytes in a file
-// this naive approach checks everything, should this be to slow, consider adding
-// an implementation of https://en.wikipedia.org/wiki/Knuth%E2%80%93Morris%E2%80%93Pratt_algorithm
+fn scan_file(
+    app_handle: &AppHandle,
+    path: &str,
+    patterns: &[BytePattern],
+    max_matches: usize,
+    scan_amount: Option<usize>,
+) -> Result<Vec<Vec<usize>>, String> {
+    let validated_path = get_allowed_path_with_string_error(app_handle, path)?;
+    let max_scan_bytes = scan_amount.map_or(u64::MAX, |scan_amount| scan_amount as u64);
+    let scan_result = || -> Result<Vec<Vec<usize>>, io::Error> {
+        let file = File::open(validated_path)?;
+        let mut reader = file.take(max_scan_bytes);
+        byte_scan::scan_for_patterns(&mut reader, patterns, max_matches)
+    }();
+    scan_result.map_err(|err| err.to_string())
+}
+
+// searches for a sequence of bytes in a file, returns the offset of the first match
 #[tauri::command]
 pub async fn scan_file_for_bytes(
     app_handle: AppHandle,
@@ -106,66 +134,89 @@ pub async fn scan_file_for_bytes(
     search_bytes: Vec<u8>,
     scan_amount: Option<usize>,
 ) -> Result<Option<usize>, String> {
-    let search_bytes = &search_bytes;
-    let validated_path = get_allowed_path_with_string_error(&app_handle, path)?;
-    if search_bytes.len() < 1 {
+    if search_bytes.is_empty() {
         return Err(String::from("Received no bytes to search for."));
     }
+    let pattern = BytePattern::exact(&search_bytes)?;
+    let matches = scan_file(&app_handle, path, &[pattern], 1, scan_amount)?;
+    Ok(matches[0].first().copied())
+}
 
-    let mut loading_buffer_size = 8 * 1024; // like current default
-    while loading_buffer_size < search_bytes.len() * 4 {
-        loading_buffer_size *= 2;
-    }
-    let max_scan_bytes = scan_amount.unwrap_or(usize::MAX);
-
-    let scan_result = || -> Result<Option<usize>, io::Error> {
-        let mut f = File::open(validated_path)?;
-
-        let offset_buffer_size = search_bytes.len() - 1;
-        let real_buffer_size = loading_buffer_size + offset_buffer_size;
-        let mut buffer: Vec<u8> = vec![0; real_buffer_size];
-
-        let mut read_start_index = 0;
-        let mut num_current_bytes;
-        while {
-            num_current_bytes = f.read(&mut buffer[offset_buffer_size..])?;
-            0 < num_current_bytes
-        } {
-            let buf_start_index = if read_start_index == 0 {
-                offset_buffer_size
-            } else {
-                0
-            };
-
-            let mut max_bytes_reached = false;
-            let buf_to_search_in = if read_start_index + num_current_bytes > max_scan_bytes {
-                max_bytes_reached = true;
-                &buffer[buf_start_index..(max_scan_bytes + offset_buffer_size - read_start_index)]
-            } else if num_current_bytes < loading_buffer_size {
-                &buffer[buf_start_index..(num_current_bytes + offset_buffer_size)]
-            } else {
-                &buffer[buf_start_index..]
-            };
-
-            let sub_index_option = buf_to_search_in
-                .windows(search_bytes.len())
-                .position(|window| window == search_bytes);
-            if let Some(sub_index) = sub_index_option {
-                return Ok(Some(
-                    sub_index + read_start_index + buf_start_index - offset_buffer_size,
-                ));
-            } else if max_bytes_reached {
-                return Ok(None);
-            }
+// searches for byte signatures like "4D 5A ?? 00" in a single pass, "??" matches any byte
+// returns the offsets of every pattern in the same order, at most max_matches per pattern
+#[tauri::command]
+pub async fn scan_file_for_patterns(
+    app_handle: AppHandle,
+    path: &str,
+    patterns: Vec<String>,
+    max_matches: Option<usize>,
+    scan_amount: Option<usize>,
+) -> Result<Vec<Vec<usize>>, String> {
+    let patterns = patterns
+        .iter()
+        .map(|pattern| BytePattern::parse(pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    scan_file(
+        &app_handle,
+        path,
+        &patterns,
+        max_matches.unwrap_or(SCAN_DEFAULT_MAX_MATCHES),
+        scan_amount,
+    )
+}
 
-            // copy for next iteration, ignored if done
-            buffer.copy_within(
-                num_current_bytes..(offset_buffer_size + num_current_bytes),
-                0,
-            );
-            read_start_index += num_current_bytes;
-        }
-        Ok(None)
-    }();
-    scan_result.map_err(|err| err.to_string())
+// async (other thread), since executables are read completely
+// parses PE headers and the version resource, so also unknown game builds can be classified
+#[tauri::command]
+pub async fn get_pe_info(app_handle: AppHandle, path: &str) -> Result<PeInfo, String> {
+    let validated_path = get_allowed_path_with_string_error(&app_handle, path)?;
+    let data = fs::read(validated_path).map_err(|err| err.to_string())?;
+    pe_info::parse_pe_info(&data)
+}
+
+// writes to a temp file first and renames it over the target, so a crash never leaves half a file
+#[tauri::command]
+pub async fn write_file_atomic(
+    app_handle: AppHandle,
+    path: &str,
+    content: Vec<u8>,
+) -> Result<(), SafeWriteError> {
+    let validated_path = get_allowed_path(&app_handle, path)?;
+    safe_write::write_atomic(validated_path, &content)
+}
+
+#[tauri::command]
+pub async fn write_text_file_atomic(
+    app_handle: AppHandle,
+    path: &str,
+    content: String,
+) -> Result<(), SafeWriteError> {
+    let validated_path = get_allowed_path(&app_handle, path)?;
+    safe_write::write_atomic(validated_path, content.as_bytes())
+}
+
+// replaces the target with a copy of the replacement, the old target is restored if anything fails
+// returns the path of the backup, if one is kept
+#[tauri::command]
+pub async fn replace_file_safely(
+    app_handle: AppHandle,
+    target: &str,
+    replacement: &str,
+    keep_backup: Option<bool>,
+) -> Result<Option<String>, SafeWriteError> {
+    let validated_target = get_allowed_path(&app_handle, target)?;
+    let validated_replacement = get_allowed_path(&app_handle, replacement)?;
+    let backup_path = safe_write::replace_with_backup(
+        validated_target,
+        validated_replacement,
+        keep_backup.unwrap_or(false),
+    )?;
+    Ok(backup_path.map(|path| path.to_slash_lossy().to_string()))
+}
+
+// puts the backup kept by "replace_file_safely" back in place
+#[tauri::command]
+pub async fn restore_file_backup(app_handle: AppHandle, path: &str) -> Result<(), SafeWriteError> {
+    let validated_path = get_allowed_path(&app_handle, path)?;
+    safe_write::restore_backup(validated_path)
 }
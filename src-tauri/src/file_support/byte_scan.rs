@@ -0,0 +1,395 @@
+use aho_corasick::{AhoCorasick, MatchKind};
+use std::io::{self, Read};
+
+const LOADING_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A byte signature, where wildcard bytes match any value.
+pub struct BytePattern {
+    bytes: Vec<Option<u8>>,
+}
+
+impl BytePattern {
+    /// Parses signatures like "4D 5A ?? 00", separated by whitespace.
+    /// A single "?" is also accepted as wildcard.
+    pub fn parse(signature: &str) -> Result<BytePattern, String> {
+        let bytes = signature
+            .split_whitespace()
+            .map(|token| match token {
+                "??" | "?" => Some(None),
+                _ if token.len() == 2 && token.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+                    u8::from_str_radix(token, 16).ok().map(Some)
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("Invalid byte pattern '{}'.", signature))?;
+        BytePattern::new(bytes)
+    }
+
+    pub fn exact(bytes: &[u8]) -> Result<BytePattern, String> {
+        BytePattern::new(bytes.iter().copied().map(Some).collect())
+    }
+
+    // only wildcards would match everywhere
+    fn new(bytes: Vec<Option<u8>>) -> Result<BytePattern, String> {
+        if bytes.iter().all(Option::is_none) {
+            return Err(String::from(
+                "Byte patterns need at least one byte that is not a wildcard.",
+            ));
+        }
+        Ok(BytePattern { bytes })
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn matches_at(&self, haystack: &[u8]) -> bool {
+        self.bytes
+            .iter()
+            .zip(haystack)
+            .all(|(pattern_byte, byte)| pattern_byte.is_none() || *pattern_byte == Some(*byte))
+    }
+}
+
+// the longest run without wildcards is searched, the rest is only compared around its hits
+fn get_anchor(pattern: &BytePattern) -> (usize, Vec<u8>) {
+    let mut best: (usize, usize) = (0, 0); // (start, length)
+    let mut run_start = 0;
+    for (index, byte) in pattern.bytes.iter().enumerate() {
+        if byte.is_none() {
+            run_start = index + 1;
+        } else if index + 1 - run_start > best.1 {
+            best = (run_start, index + 1 - run_start);
+        }
+    }
+    let anchor = pattern.bytes[best.0..(best.0 + best.1)]
+        .iter()
+        .flatten()
+        .copied()
+        .collect();
+    (best.0, anchor)
+}
+
+/// Searches all patterns in a single pass over the reader.
+/// Returns the offsets of every pattern, but at most `max_matches` per pattern.
+/// Reading stops if every pattern reached the maximum.
+///
+/// The wildcard free parts of all patterns are searched together with Aho-Corasick,
+/// so the time grows linear with the file size, independent of the number of patterns.
+pub fn scan_for_patterns<R: Read>(
+    reader: &mut R,
+    patterns: &[BytePattern],
+    max_matches: usize,
+) -> Result<Vec<Vec<usize>>, io::Error> {
+    let mut matches: Vec<Vec<usize>> = patterns.iter().map(|_| Vec::new()).collect();
+    let max_pattern_len = patterns.iter().map(BytePattern::len).max().unwrap_or(0);
+    if max_pattern_len == 0 || max_matches == 0 {
+        return Ok(matches);
+    }
+
+    // equal anchors are only searched once and map to all their patterns
+    let mut anchor_offsets: Vec<usize> = Vec::with_capacity(patterns.len()); // anchor start per pattern
+    let mut anchors: Vec<Vec<u8>> = Vec::new();
+    let mut anchor_patterns: Vec<Vec<usize>> = Vec::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        let (anchor_offset, anchor) = get_anchor(pattern);
+        anchor_offsets.push(anchor_offset);
+        match anchors.iter().position(|known| *known == anchor) {
+            Some(anchor_index) => anchor_patterns[anchor_index].push(index),
+            None => {
+                anchors.push(anchor);
+                anchor_patterns.push(vec![index]);
+            }
+        }
+    }
+    let searcher = AhoCorasick::builder()
+        .match_kind(MatchKind::Standard) // required to get overlapping hits
+        .build(&anchors)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    // the end of the previous read is kept, so patterns crossing the border are found
+    let overlap_size = max_pattern_len - 1;
+    let mut buffer: Vec<u8> = vec![0; overlap_size + LOADING_BUFFER_SIZE];
+    let mut buffer_len = 0; // valid bytes in the buffer
+    let mut buffer_offset = 0; // file offset of the first byte in the buffer
+    let mut next_offsets: Vec<usize> = vec![0; patterns.len()]; // first start not checked per pattern
+
+    loop {
+        let read_bytes = match reader.read(&mut buffer[buffer_len..]) {
+            Ok(0) => return Ok(matches),
+            Ok(read_bytes) => read_bytes,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        buffer_len += read_bytes;
+        let buffer_end = buffer_offset + buffer_len;
+
+        // hits are ordered by their end, so the starts of every pattern stay ordered
+        for hit in searcher.find_overlapping_iter(&buffer[..buffer_len]) {
+            for &index in &anchor_patterns[hit.pattern().as_usize()] {
+                let pattern = &patterns[index];
+                let hit_offset = buffer_offset + hit.start();
+                if matches[index].len() >= max_matches || hit_offset < anchor_offsets[index] {
+                    continue;
+                }
+                let start = hit_offset - anchor_offsets[index];
+                if start < next_offsets[index] || start + pattern.len() > buffer_end {
+                    continue;
+                }
+                if pattern.matches_at(&buffer[(start - buffer_offset)..buffer_len]) {
+                    matches[index].push(start);
+                }
+                next_offsets[index] = start + 1;
+            }
+        }
+        for (index, pattern) in patterns.iter().enumerate() {
+            if buffer_end >= pattern.len() {
+                next_offsets[index] = next_offsets[index].max(buffer_end - pattern.len() + 1);
+            }
+        }
+        if matches.iter().all(|offsets| offsets.len() >= max_matches) {
+            return Ok(matches);
+        }
+
+        // keep the bytes that might still be the start of a match
+        if buffer_len > overlap_size {
+            buffer.copy_within((buffer_len - overlap_size)..buffer_len, 0);
+            buffer_offset += buffer_len - overlap_size;
+            buffer_len = overlap_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, time::Instant};
+
+    // hands out the data in small, changing pieces, like pipes or network drives
+    struct ShortReader<'a> {
+        data: &'a [u8],
+        position: usize,
+        reads: usize,
+    }
+
+    impl Read for ShortReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            let step = 1 + self.reads % 7;
+            let count = step.min(buf.len()).min(self.data.len() - self.position);
+            buf[..count].copy_from_slice(&self.data[self.position..(self.position + count)]);
+            self.position += count;
+            Ok(count)
+        }
+    }
+
+    fn brute_force(data: &[u8], patterns: &[BytePattern], max_matches: usize) -> Vec<Vec<usize>> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                (0..data.len())
+                    .filter(|&start| {
+                        start + pattern.len() <= data.len() && pattern.matches_at(&data[start..])
+                    })
+                    .take(max_matches)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn scan(data: &[u8], patterns: &[BytePattern], max_matches: usize) -> Vec<Vec<usize>> {
+        let result = scan_for_patterns(&mut Cursor::new(data), patterns, max_matches).unwrap();
+        assert_eq!(result, brute_force(data, patterns, max_matches));
+        result
+    }
+
+    fn parse(signatures: &[&str]) -> Vec<BytePattern> {
+        signatures
+            .iter()
+            .map(|signature| BytePattern::parse(signature).unwrap())
+            .collect()
+    }
+
+    // deterministic noise without a dependency
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_rejects_invalid_patterns() {
+        assert!(BytePattern::parse("4D 5A ?? ?").is_ok());
+        assert!(BytePattern::parse("4D 5").is_err());
+        assert!(BytePattern::parse("4D XX").is_err());
+        assert!(BytePattern::parse("?? ??").is_err());
+        assert!(BytePattern::exact(&[]).is_err());
+    }
+
+    #[test]
+    fn finds_matches_across_the_buffer_border() {
+        let patterns = parse(&["DE AD BE EF", "AD ?? EF"]);
+        for start in (LOADING_BUFFER_SIZE - 4)..=LOADING_BUFFER_SIZE {
+            let mut data = vec![0; 2 * LOADING_BUFFER_SIZE];
+            data[start..(start + 4)].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+            let result = scan(&data, &patterns, 10);
+            assert_eq!(result, vec![vec![start], vec![start + 1]]);
+        }
+    }
+
+    #[test]
+    fn finds_patterns_longer_than_the_overlap_of_shorter_ones() {
+        // the overlap is set by the longest pattern, the short ones must not be reported twice
+        let mut data = noise(3 * LOADING_BUFFER_SIZE, 1);
+        let long: Vec<u8> = (0..=255).cycle().take(300).collect();
+        let position = LOADING_BUFFER_SIZE - 150;
+        data[position..(position + long.len())].copy_from_slice(&long);
+        let patterns = vec![
+            BytePattern::exact(&long).unwrap(),
+            BytePattern::exact(&long[140..160]).unwrap(),
+            BytePattern::exact(&[data[10]]).unwrap(),
+        ];
+        let result = scan(&data, &patterns, usize::MAX);
+        assert_eq!(result[0], vec![position]);
+        assert!(result[1].contains(&(position + 140)));
+    }
+
+    #[test]
+    fn finds_patterns_longer_than_the_loading_buffer() {
+        let mut data = noise(4 * LOADING_BUFFER_SIZE, 2);
+        let pattern = data[1000..(1000 + LOADING_BUFFER_SIZE + 10)].to_vec();
+        let position = 2 * LOADING_BUFFER_SIZE - 77;
+        data[position..(position + pattern.len())].copy_from_slice(&pattern);
+        let result = scan(&data, &[BytePattern::exact(&pattern).unwrap()], 10);
+        assert_eq!(result, vec![vec![1000, position]]);
+    }
+
+    #[test]
+    fn respects_leading_and_trailing_wildcards() {
+        let data = [0xAA, 0x01, 0x02, 0xBB, 0x01, 0x02];
+        let patterns = parse(&["?? 01 02", "01 02 ??", "?? ?? 01 02 ?? ??"]);
+        let result = scan(&data, &patterns, 10);
+        // the wildcards still need bytes, so matches at the start or end of the data are cut
+        assert_eq!(result, vec![vec![0, 3], vec![1], vec![]]);
+    }
+
+    #[test]
+    fn reports_overlapping_matches() {
+        let data = [0x11; 8];
+        let patterns = parse(&["11 11 11", "11 ?? 11", "11"]);
+        let result = scan(&data, &patterns, 100);
+        assert_eq!(result[0], vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(result[1], vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(result[2], (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shares_equal_anchors_between_patterns() {
+        let data = [0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0x05];
+        let patterns = parse(&["01 02 03", "01 02 ?? 04", "01 02"]);
+        let result = scan(&data, &patterns, 10);
+        assert_eq!(result, vec![vec![0], vec![0], vec![0, 4]]);
+    }
+
+    #[test]
+    fn stops_at_max_matches() {
+        let data = vec![0x42; 3 * LOADING_BUFFER_SIZE];
+        let patterns = parse(&["42 42", "42 ?? 42 43"]);
+        let result = scan(&data, &patterns, 5);
+        assert_eq!(result, vec![vec![0, 1, 2, 3, 4], vec![]]);
+        assert!(scan(&data, &patterns, 0).iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn handles_short_reads() {
+        let mut data = noise(LOADING_BUFFER_SIZE + 500, 3);
+        for position in [
+            0,
+            5,
+            400,
+            LOADING_BUFFER_SIZE - 2,
+            LOADING_BUFFER_SIZE + 490,
+        ] {
+            data[position..(position + 4)].copy_from_slice(&[0xCA, 0xFE, 0xBA, 0xBE]);
+        }
+        let patterns = parse(&["CA FE BA BE", "CA ?? ?? BE", "FE"]);
+        let mut reader = ShortReader {
+            data: &data,
+            position: 0,
+            reads: 0,
+        };
+        let result = scan_for_patterns(&mut reader, &patterns, usize::MAX).unwrap();
+        assert_eq!(result, brute_force(&data, &patterns, usize::MAX));
+        assert_eq!(
+            result[0],
+            vec![
+                0,
+                5,
+                400,
+                LOADING_BUFFER_SIZE - 2,
+                LOADING_BUFFER_SIZE + 490
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_brute_force_on_random_data() {
+        // a small alphabet creates many partial and overlapping hits
+        for seed in 0..20 {
+            let data: Vec<u8> = noise(2 * LOADING_BUFFER_SIZE + 123, seed)
+                .into_iter()
+                .map(|byte| byte % 4)
+                .collect();
+            let patterns = parse(&["00 01 02", "?? 03 03 ??", "01 ?? ?? 01 02 03", "02"]);
+            scan(&data, &patterns, 1 + seed as usize * 500);
+        }
+    }
+
+    // run with "cargo test --release bench -- --ignored --nocapture"
+    #[test]
+    #[ignore]
+    fn bench_scan_3mb() {
+        let mut data = noise(3 * 1024 * 1024, 4);
+        let signatures = [
+            "55 8B EC 83 EC ?? 53 56 57",
+            "E8 ?? ?? ?? ?? 83 C4 04",
+            "68 ?? ?? ?? ?? FF 15 ?? ?? ?? ??",
+            "8B 0D ?? ?? ?? ?? 85 C9 74",
+            "C7 05 ?? ?? ?? ?? 01 00 00 00",
+        ];
+        for (index, position) in (0..data.len()).step_by(100_003).enumerate() {
+            let pattern = &parse(&signatures[(index % signatures.len())..][..1])[0];
+            for (offset, byte) in pattern.bytes.iter().enumerate() {
+                if let (Some(byte), Some(target)) = (byte, data.get_mut(position + offset)) {
+                    *target = *byte;
+                }
+            }
+        }
+        let patterns = parse(&signatures);
+
+        let start = Instant::now();
+        let runs = 20;
+        for _ in 0..runs {
+            scan_for_patterns(&mut Cursor::new(&data), &patterns, usize::MAX).unwrap();
+        }
+        let elapsed = start.elapsed() / runs;
+        println!(
+            "scan of {} bytes for {} patterns: {:?} ({:.0} MB/s)",
+            data.len(),
+            patterns.len(),
+            elapsed,
+            data.len() as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+        );
+        assert_eq!(
+            scan_for_patterns(&mut Cursor::new(&data), &patterns, usize::MAX).unwrap(),
+            brute_force(&data, &patterns, usize::MAX)
+        );
+    }
+}
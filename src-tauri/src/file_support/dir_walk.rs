@@ -0,0 +1,144 @@
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use tauri::scope::GlobPattern;
+
+use crate::constants::PATH_MATCH_OPTIONS;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadDirOptions {
+    /// 1 only lists the direct children, no limit if not set.
+    pub max_depth: Option<usize>,
+    pub files_only: bool,
+    pub dirs_only: bool,
+    /// Globs relative to the base, like ".cache/**", matching folders are not entered.
+    pub exclude: Vec<String>,
+    /// Symlinks are listed, but only followed into folders if set.
+    pub follow_symlinks: bool,
+}
+
+/// A listed file or folder, the path uses slashes.
+#[derive(Serialize)]
+pub struct DirEntryInfo {
+    pub path: String,
+    pub is_dir: bool,
+    /// Zero for folders.
+    pub size: u64,
+    /// Milliseconds since the unix epoch, if the system provides it.
+    pub modified: Option<u64>,
+}
+
+fn get_modified_millis(metadata: &Metadata) -> Option<u64> {
+    let duration = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(duration.as_millis()).ok()
+}
+
+struct DirWalker<'a, F: Fn(&Path) -> bool> {
+    base: &'a Path,
+    options: &'a ReadDirOptions,
+    exclude: Vec<GlobPattern>,
+    is_allowed: F,
+    visited_dirs: HashSet<PathBuf>, // canonical paths, against symlink loops
+    entries: Vec<DirEntryInfo>,
+}
+
+impl<'a, F: Fn(&Path) -> bool> DirWalker<'a, F> {
+    // folders are also checked with a trailing slash, so "folder/**" skips the folder itself
+    fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let relative_path = match path.strip_prefix(self.base) {
+            Ok(relative_path) => relative_path.to_slash_lossy(),
+            Err(_) => return false,
+        };
+        let folder_path = format!("{}/", relative_path);
+        self.exclude.iter().any(|glob| {
+            glob.matches_with(&relative_path, PATH_MATCH_OPTIONS)
+                || (is_dir && glob.matches_with(&folder_path, PATH_MATCH_OPTIONS))
+        })
+    }
+
+    fn walk(&mut self, dir: &Path, depth: usize) -> io::Result<()> {
+        if self
+            .options
+            .max_depth
+            .is_some_and(|max_depth| depth > max_depth)
+        {
+            return Ok(());
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let link_metadata = fs::symlink_metadata(&path)?;
+            let is_symlink = link_metadata.file_type().is_symlink();
+            let metadata = if is_symlink && self.options.follow_symlinks {
+                match fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(_) => link_metadata, // broken links are listed as they are
+                }
+            } else {
+                link_metadata
+            };
+            let is_dir = metadata.is_dir();
+            if self.is_excluded(&path, is_dir) {
+                continue;
+            }
+
+            let is_listed = if is_dir {
+                !self.options.files_only
+            } else {
+                !self.options.dirs_only
+            };
+            if is_listed && (self.is_allowed)(&path) {
+                if let Some(path_string) = path.to_slash() {
+                    self.entries.push(DirEntryInfo {
+                        path: path_string.to_string(),
+                        is_dir,
+                        size: if is_dir { 0 } else { metadata.len() },
+                        modified: get_modified_millis(&metadata),
+                    });
+                }
+            }
+
+            if is_dir && (!is_symlink || self.enter_linked_dir(&path)) {
+                self.walk(&path, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn enter_linked_dir(&mut self, path: &Path) -> bool {
+        match dunce::canonicalize(path) {
+            Ok(target) => self.visited_dirs.insert(target),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Lists the content of the folder depth first, every folder is followed by its content.
+/// Only entries accepted by `is_allowed` are returned, but all folders are entered.
+pub fn read_dir_entries<F: Fn(&Path) -> bool>(
+    base: &Path,
+    options: &ReadDirOptions,
+    is_allowed: F,
+) -> Result<Vec<DirEntryInfo>, String> {
+    let exclude = options
+        .exclude
+        .iter()
+        .map(|pattern| GlobPattern::new(pattern).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut walker = DirWalker {
+        base,
+        options,
+        exclude,
+        is_allowed,
+        visited_dirs: HashSet::from([base.to_path_buf()]),
+        entries: Vec::new(),
+    };
+    walker.walk(base, 1).map_err(|err| err.to_string())?;
+    Ok(walker.entries)
+}
@@ -0,0 +1,343 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const PE_SIGNATURE: &[u8] = b"PE\0\0";
+const OPTIONAL_HEADER_MAGIC_PE32: u16 = 0x10b;
+const OPTIONAL_HEADER_MAGIC_PE32_PLUS: u16 = 0x20b;
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const RESOURCE_DATA_DIRECTORY_INDEX: usize = 2;
+const RESOURCE_TYPE_VERSION: u32 = 16;
+const RESOURCE_ENTRY_IS_DIRECTORY: u32 = 0x8000_0000;
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF_04BD;
+const VERSION_BLOCK_HEADER_SIZE: usize = 6;
+
+#[derive(Serialize)]
+pub struct PeSection {
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    pub raw_size: u32,
+    pub characteristics: u32,
+}
+
+/// The VS_VERSIONINFO resource of an executable.
+#[derive(Serialize, Default)]
+pub struct PeVersionInfo {
+    /// From the fixed file info, as "major.minor.build.revision".
+    pub file_version: Option<String>,
+    pub product_version: Option<String>,
+    pub product_name: Option<String>,
+    /// Windows language id, like 0x0409 for English (US).
+    pub language: Option<u16>,
+    pub code_page: Option<u16>,
+    /// All entries of the used string table, like "CompanyName" or "FileDescription".
+    pub strings: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct PeInfo {
+    pub machine: u16,
+    pub is_64_bit: bool,
+    /// Seconds since the unix epoch, set by the linker.
+    pub link_timestamp: u32,
+    pub image_size: u32,
+    pub image_base: u64,
+    pub entry_point: u32,
+    pub sections: Vec<PeSection>,
+    /// Not every executable has a version resource.
+    pub version_info: Option<PeVersionInfo>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some((read_u32(data, offset)? as u64) | ((read_u32(data, offset + 4)? as u64) << 32))
+}
+
+fn align_4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn to_version_string(most_significant: u32, least_significant: u32) -> String {
+    format!(
+        "{}.{}.{}.{}",
+        most_significant >> 16,
+        most_significant & 0xFFFF,
+        least_significant >> 16,
+        least_significant & 0xFFFF
+    )
+}
+
+// reads a null terminated UTF-16 string, returns it and the offset after the terminator
+fn read_utf16(data: &[u8], offset: usize, end: usize) -> (String, usize) {
+    let mut units = Vec::new();
+    let mut position = offset;
+    while position + 2 <= end {
+        let unit = read_u16(data, position).unwrap_or(0);
+        position += 2;
+        if unit == 0 {
+            break;
+        }
+        units.push(unit);
+    }
+    (String::from_utf16_lossy(&units), position)
+}
+
+// maps a relative virtual address to an offset in the file
+fn rva_to_offset(sections: &[PeSection], section_offsets: &[u32], rva: u32) -> Option<usize> {
+    sections
+        .iter()
+        .zip(section_offsets)
+        .find(|(section, _)| {
+            let size = section.virtual_size.max(section.raw_size);
+            rva >= section.virtual_address && rva - section.virtual_address < size
+        })
+        .and_then(|(section, raw_offset)| {
+            let relative = rva - section.virtual_address;
+            (relative < section.raw_size).then(|| *raw_offset as usize + relative as usize)
+        })
+}
+
+/// A node of the version resource: a key, an optional value and child nodes.
+struct VersionBlock {
+    key: String,
+    value_start: usize,
+    value_end: usize,
+    is_text: bool,
+    children_start: usize,
+    end: usize,
+}
+
+fn read_version_block(data: &[u8], offset: usize, parent_end: usize) -> Option<VersionBlock> {
+    let length = read_u16(data, offset)? as usize;
+    if length < VERSION_BLOCK_HEADER_SIZE || offset + length > parent_end {
+        return None;
+    }
+    let end = offset + length;
+    let value_length = read_u16(data, offset + 2)? as usize;
+    let is_text = read_u16(data, offset + 4)? == 1;
+    let (key, key_end) = read_utf16(data, offset + VERSION_BLOCK_HEADER_SIZE, end);
+    let value_start = align_4(key_end).min(end);
+    // text lengths are given in characters, but some compilers write bytes, so the end is capped
+    let value_bytes = if is_text {
+        value_length * 2
+    } else {
+        value_length
+    };
+    let value_end = (value_start + value_bytes).min(end);
+    Some(VersionBlock {
+        key,
+        value_start,
+        value_end,
+        is_text,
+        children_start: align_4(value_end).min(end),
+        end,
+    })
+}
+
+fn read_version_children(data: &[u8], parent: &VersionBlock) -> Vec<VersionBlock> {
+    let mut children = Vec::new();
+    let mut offset = parent.children_start;
+    while offset + VERSION_BLOCK_HEADER_SIZE <= parent.end {
+        match read_version_block(data, offset, parent.end) {
+            Some(child) => {
+                offset = align_4(child.end);
+                children.push(child);
+            }
+            None => break,
+        }
+    }
+    children
+}
+
+fn parse_version_info(data: &[u8], resource_language: u16) -> Option<PeVersionInfo> {
+    let root = read_version_block(data, 0, data.len())?;
+    if root.key != "VS_VERSION_INFO" {
+        return None;
+    }
+    let mut version_info = PeVersionInfo::default();
+
+    if read_u32(data, root.value_start) == Some(FIXED_FILE_INFO_SIGNATURE)
+        && root.value_end - root.value_start >= 24
+    {
+        let fixed = |index: usize| read_u32(data, root.value_start + index * 4).unwrap_or(0);
+        version_info.file_version = Some(to_version_string(fixed(2), fixed(3)));
+        version_info.product_version = Some(to_version_string(fixed(4), fixed(5)));
+    }
+
+    let mut string_tables = Vec::new();
+    for child in read_version_children(data, &root) {
+        match child.key.as_str() {
+            "StringFileInfo" => string_tables.extend(read_version_children(data, &child)),
+            "VarFileInfo" => {
+                let translation = read_version_children(data, &child)
+                    .into_iter()
+                    .find(|var| var.key == "Translation" && var.value_end - var.value_start >= 4);
+                if let Some(translation) = translation {
+                    version_info.language = read_u16(data, translation.value_start);
+                    version_info.code_page = read_u16(data, translation.value_start + 2);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // string tables are keyed by language and code page as hex, like "040904b0"
+    let table_language = |table: &VersionBlock| u16::from_str_radix(table.key.get(..4)?, 16).ok();
+    let language = version_info.language.unwrap_or(resource_language);
+    let string_table = string_tables
+        .iter()
+        .find(|table| table_language(table) == Some(language))
+        .or_else(|| string_tables.first());
+    if let Some(string_table) = string_table {
+        if version_info.language.is_none() {
+            version_info.language = table_language(string_table);
+            version_info.code_page = string_table
+                .key
+                .get(4..8)
+                .and_then(|code_page| u16::from_str_radix(code_page, 16).ok());
+        }
+        for string in read_version_children(data, string_table) {
+            let value = if string.is_text {
+                read_utf16(data, string.value_start, string.end).0
+            } else {
+                String::new()
+            };
+            version_info.strings.insert(string.key, value);
+        }
+    }
+    if version_info.language.is_none() && resource_language != 0 {
+        version_info.language = Some(resource_language);
+    }
+    version_info.product_name = version_info.strings.get("ProductName").cloned();
+    Some(version_info)
+}
+
+// returns the first entry of a resource directory, or the one with the given id
+fn find_resource_entry(resources: &[u8], directory: usize, id: Option<u32>) -> Option<(u32, u32)> {
+    let named_entries = read_u16(resources, directory + 12)? as usize;
+    let id_entries = read_u16(resources, directory + 14)? as usize;
+    (0..(named_entries + id_entries))
+        .map(|index| directory + 16 + index * 8)
+        .filter_map(|entry| Some((read_u32(resources, entry)?, read_u32(resources, entry + 4)?)))
+        .find(|(entry_id, _)| id.is_none() || id == Some(*entry_id))
+}
+
+// resource tree is type -> name -> language -> data, the first name and language are used
+fn find_version_resource<'a>(
+    data: &'a [u8],
+    sections: &[PeSection],
+    section_offsets: &[u32],
+    resource_rva: u32,
+) -> Option<(&'a [u8], u16)> {
+    let resource_offset = rva_to_offset(sections, section_offsets, resource_rva)?;
+    let resources = data.get(resource_offset..)?;
+    let subdirectory = |offset: u32| {
+        (offset & RESOURCE_ENTRY_IS_DIRECTORY != 0)
+            .then_some((offset & !RESOURCE_ENTRY_IS_DIRECTORY) as usize)
+    };
+
+    let (_, type_offset) = find_resource_entry(resources, 0, Some(RESOURCE_TYPE_VERSION))?;
+    let (_, name_offset) = find_resource_entry(resources, subdirectory(type_offset)?, None)?;
+    let (language, data_entry) = find_resource_entry(resources, subdirectory(name_offset)?, None)?;
+    if data_entry & RESOURCE_ENTRY_IS_DIRECTORY != 0 {
+        return None;
+    }
+
+    let data_rva = read_u32(resources, data_entry as usize)?;
+    let data_size = read_u32(resources, data_entry as usize + 4)? as usize;
+    let data_offset = rva_to_offset(sections, section_offsets, data_rva)?;
+    let version_data = data.get(data_offset..data_offset.checked_add(data_size)?)?;
+    Some((version_data, language as u16))
+}
+
+/// Parses the headers, sections and version resource of a PE executable.
+pub fn parse_pe_info(data: &[u8]) -> Result<PeInfo, String> {
+    parse_headers(data).ok_or_else(|| String::from("Not a valid PE file."))
+}
+
+fn parse_headers(data: &[u8]) -> Option<PeInfo> {
+    if data.get(..2)? != b"MZ" {
+        return None;
+    }
+    let pe_offset = read_u32(data, 0x3C)? as usize;
+    if data.get(pe_offset..pe_offset.checked_add(4)?)? != PE_SIGNATURE {
+        return None;
+    }
+
+    let coff_offset = pe_offset + 4;
+    let machine = read_u16(data, coff_offset)?;
+    let number_of_sections = read_u16(data, coff_offset + 2)? as usize;
+    let link_timestamp = read_u32(data, coff_offset + 4)?;
+    let optional_header_size = read_u16(data, coff_offset + 16)? as usize;
+
+    let optional_offset = coff_offset + COFF_HEADER_SIZE;
+    let is_64_bit = match read_u16(data, optional_offset)? {
+        OPTIONAL_HEADER_MAGIC_PE32 => false,
+        OPTIONAL_HEADER_MAGIC_PE32_PLUS => true,
+        _ => return None,
+    };
+    let entry_point = read_u32(data, optional_offset + 16)?;
+    let image_size = read_u32(data, optional_offset + 56)?;
+    let (image_base, data_directories_offset) = if is_64_bit {
+        (read_u64(data, optional_offset + 24)?, optional_offset + 112)
+    } else {
+        (
+            read_u32(data, optional_offset + 28)? as u64,
+            optional_offset + 96,
+        )
+    };
+    let number_of_data_directories = read_u32(data, data_directories_offset - 4)? as usize;
+
+    let sections_offset = optional_offset + optional_header_size;
+    let mut sections = Vec::with_capacity(number_of_sections);
+    let mut section_offsets = Vec::with_capacity(number_of_sections);
+    for index in 0..number_of_sections {
+        let header = sections_offset + index * SECTION_HEADER_SIZE;
+        let name_bytes = data.get(header..header + 8)?;
+        let name_end = name_bytes.iter().position(|byte| *byte == 0).unwrap_or(8);
+        sections.push(PeSection {
+            name: String::from_utf8_lossy(&name_bytes[..name_end]).to_string(),
+            virtual_size: read_u32(data, header + 8)?,
+            virtual_address: read_u32(data, header + 12)?,
+            raw_size: read_u32(data, header + 16)?,
+            characteristics: read_u32(data, header + 36)?,
+        });
+        section_offsets.push(read_u32(data, header + 20)?);
+    }
+
+    // a broken version resource should not hide the rest of the information
+    let version_info = (number_of_data_directories > RESOURCE_DATA_DIRECTORY_INDEX)
+        .then(|| {
+            read_u32(
+                data,
+                data_directories_offset + RESOURCE_DATA_DIRECTORY_INDEX * 8,
+            )
+        })
+        .flatten()
+        .filter(|resource_rva| *resource_rva != 0)
+        .and_then(|resource_rva| {
+            find_version_resource(data, &sections, &section_offsets, resource_rva)
+        })
+        .and_then(|(version_data, language)| parse_version_info(version_data, language));
+
+    Some(PeInfo {
+        machine,
+        is_64_bit,
+        link_timestamp,
+        image_size,
+        image_base,
+        entry_point,
+        sections,
+        version_info,
+    })
+}
@@ -0,0 +1,315 @@
+use serde::ser::SerializeStruct;
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    constants::{FILE_BACKUP_SUFFIX, FILE_TEMP_SUFFIX},
+    utils::with_suffix,
+};
+
+// windows reports files opened by other programs as sharing or lock violation,
+// linux only refuses to write running executables
+#[cfg(windows)]
+const LOCKED_OS_ERRORS: [i32; 2] = [32, 33]; // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+#[cfg(not(windows))]
+const LOCKED_OS_ERRORS: [i32; 1] = [26]; // ETXTBSY
+
+/// Error of the safe file writes, serialized as object with a code the frontend can react to,
+/// a readable message and the path that caused it.
+#[derive(Debug, thiserror::Error)]
+pub enum SafeWriteError {
+    #[error("The path '{}' is not allowed.", .0.display())]
+    NotAllowed(PathBuf),
+
+    #[error("There is no backup of '{}'.", .0.display())]
+    BackupMissing(PathBuf),
+
+    #[error("{source} ('{}')", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl SafeWriteError {
+    fn io(path: &Path) -> impl FnOnce(io::Error) -> SafeWriteError + '_ {
+        move |source| SafeWriteError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SafeWriteError::NotAllowed(_) => "file.path.not.allowed",
+            SafeWriteError::BackupMissing(_) => "file.backup.missing",
+            SafeWriteError::Io { source, .. } => {
+                if source
+                    .raw_os_error()
+                    .is_some_and(|code| LOCKED_OS_ERRORS.contains(&code))
+                {
+                    return "file.locked";
+                }
+                match source.kind() {
+                    io::ErrorKind::NotFound => "file.not.found",
+                    io::ErrorKind::PermissionDenied => "file.permission.denied",
+                    _ => "file.error",
+                }
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            SafeWriteError::NotAllowed(path)
+            | SafeWriteError::BackupMissing(path)
+            | SafeWriteError::Io { path, .. } => path,
+        }
+    }
+}
+
+impl From<tauri::Error> for SafeWriteError {
+    fn from(error: tauri::Error) -> Self {
+        match error {
+            tauri::Error::PathNotAllowed(path) => SafeWriteError::NotAllowed(path),
+            error => SafeWriteError::Io {
+                path: PathBuf::new(),
+                source: io::Error::new(io::ErrorKind::Other, error.to_string()),
+            },
+        }
+    }
+}
+
+// for callers that only report io errors, like the zip support
+impl From<SafeWriteError> for io::Error {
+    fn from(error: SafeWriteError) -> Self {
+        match error {
+            SafeWriteError::NotAllowed(_) => {
+                io::Error::new(io::ErrorKind::PermissionDenied, error.to_string())
+            }
+            SafeWriteError::BackupMissing(_) => {
+                io::Error::new(io::ErrorKind::NotFound, error.to_string())
+            }
+            SafeWriteError::Io { source, .. } => source,
+        }
+    }
+}
+
+impl serde::Serialize for SafeWriteError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        let mut state = serializer.serialize_struct("SafeWriteError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("path", &self.path().to_string_lossy())?;
+        state.end()
+    }
+}
+
+fn get_backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, FILE_BACKUP_SUFFIX)
+}
+
+// the parent needs to be synced on unix, so the rename itself survives a crash
+#[cfg(unix)]
+fn sync_parent(path: &Path) {
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) {}
+
+// the temp file is in the same folder, so the rename does not need to copy
+fn write_temp_and_rename<F: FnOnce(&mut File) -> io::Result<()>>(
+    path: &Path,
+    write: F,
+) -> Result<(), SafeWriteError> {
+    let temp_path = with_suffix(path, FILE_TEMP_SUFFIX);
+    let result = (|| -> Result<(), SafeWriteError> {
+        let mut file = File::create(&temp_path).map_err(SafeWriteError::io(&temp_path))?;
+        write(&mut file).map_err(SafeWriteError::io(&temp_path))?;
+        file.sync_all().map_err(SafeWriteError::io(&temp_path))?;
+        drop(file);
+        fs::rename(&temp_path, path).map_err(SafeWriteError::io(path))
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    } else {
+        sync_parent(path);
+    }
+    result
+}
+
+/// Writes the content to a temp file and renames it over the target,
+/// so the target either keeps its old content or has the complete new one.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), SafeWriteError> {
+    write_temp_and_rename(path, |file| file.write_all(content))
+}
+
+/// Replaces the target with a copy of the replacement.
+/// The target is moved to a backup first, which is put back if the replacement fails.
+/// An older backup is kept instead, so it always holds the file before the first replacement.
+/// Returns the backup path if a backup remains, so it can be restored later.
+pub fn replace_with_backup(
+    target: &Path,
+    replacement: &Path,
+    keep_backup: bool,
+) -> Result<Option<PathBuf>, SafeWriteError> {
+    let mut source = File::open(replacement).map_err(SafeWriteError::io(replacement))?;
+    let backup_path = get_backup_path(target);
+    let has_older_backup = backup_path.is_file();
+    let moves_target = !has_older_backup && target.exists();
+    // a locked target fails here, before anything was changed
+    if moves_target {
+        fs::rename(target, &backup_path).map_err(SafeWriteError::io(target))?;
+    }
+
+    if let Err(err) = write_temp_and_rename(target, |file| io::copy(&mut source, file).map(|_| ()))
+    {
+        if moves_target {
+            fs::rename(&backup_path, target).map_err(SafeWriteError::io(target))?;
+        }
+        return Err(err);
+    }
+
+    if has_older_backup || (moves_target && keep_backup) {
+        return Ok(Some(backup_path));
+    }
+    if moves_target {
+        fs::remove_file(&backup_path).map_err(SafeWriteError::io(&backup_path))?;
+    }
+    Ok(None)
+}
+
+/// Puts the backup of a previous replacement back in place of the target.
+pub fn restore_backup(target: &Path) -> Result<(), SafeWriteError> {
+    let backup_path = get_backup_path(target);
+    if !backup_path.is_file() {
+        return Err(SafeWriteError::BackupMissing(target.to_path_buf()));
+    }
+    fs::rename(&backup_path, target).map_err(SafeWriteError::io(target))?;
+    sync_parent(target);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_files(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn writes_atomic_without_leftovers() {
+        let dir = write_files(&[("target.txt", "old")]);
+        let target = dir.path().join("target.txt");
+        write_atomic(&target, b"new").unwrap();
+        write_atomic(&dir.path().join("created.txt"), b"created").unwrap();
+
+        assert_eq!(read(&target), "new");
+        assert_eq!(read(&dir.path().join("created.txt")), "created");
+        assert!(!with_suffix(&target, FILE_TEMP_SUFFIX).exists());
+    }
+
+    #[test]
+    fn failed_atomic_write_keeps_the_target() {
+        let dir = write_files(&[("target.txt", "old")]);
+        let target = dir.path().join("target.txt");
+        // the temp file can not be created where a folder is
+        fs::create_dir(with_suffix(&target, FILE_TEMP_SUFFIX)).unwrap();
+
+        assert!(write_atomic(&target, b"new").is_err());
+        assert_eq!(read(&target), "old");
+    }
+
+    #[test]
+    fn replaces_and_removes_the_backup() {
+        let dir = write_files(&[("target.txt", "old"), ("replacement.txt", "new")]);
+        let target = dir.path().join("target.txt");
+        let backup_path =
+            replace_with_backup(&target, &dir.path().join("replacement.txt"), false).unwrap();
+
+        assert_eq!(backup_path, None);
+        assert_eq!(read(&target), "new");
+        assert_eq!(read(&dir.path().join("replacement.txt")), "new");
+        assert!(!get_backup_path(&target).exists());
+    }
+
+    #[test]
+    fn replaces_missing_targets_without_backup() {
+        let dir = write_files(&[("replacement.txt", "new")]);
+        let target = dir.path().join("target.txt");
+        let backup_path =
+            replace_with_backup(&target, &dir.path().join("replacement.txt"), true).unwrap();
+
+        assert_eq!(backup_path, None);
+        assert_eq!(read(&target), "new");
+    }
+
+    #[test]
+    fn keeps_the_oldest_backup_and_restores_it() {
+        let dir = write_files(&[
+            ("target.txt", "original"),
+            ("first.txt", "first"),
+            ("second.txt", "second"),
+        ]);
+        let target = dir.path().join("target.txt");
+        let backup_path =
+            replace_with_backup(&target, &dir.path().join("first.txt"), true).unwrap();
+        assert_eq!(backup_path, Some(get_backup_path(&target)));
+
+        // the older backup is kept, even if no backup is requested
+        let backup_path =
+            replace_with_backup(&target, &dir.path().join("second.txt"), false).unwrap();
+        assert_eq!(backup_path, Some(get_backup_path(&target)));
+        assert_eq!(read(&target), "second");
+        assert_eq!(read(&get_backup_path(&target)), "original");
+
+        restore_backup(&target).unwrap();
+        assert_eq!(read(&target), "original");
+        assert!(!get_backup_path(&target).exists());
+    }
+
+    #[test]
+    fn failed_replacement_restores_the_target() {
+        let dir = write_files(&[("target.txt", "old"), ("replacement.txt", "new")]);
+        let target = dir.path().join("target.txt");
+        fs::create_dir(with_suffix(&target, FILE_TEMP_SUFFIX)).unwrap();
+
+        assert!(replace_with_backup(&target, &dir.path().join("replacement.txt"), true).is_err());
+        assert_eq!(read(&target), "old");
+        assert!(!get_backup_path(&target).exists());
+    }
+
+    #[test]
+    fn reports_missing_files_with_codes() {
+        let dir = write_files(&[("target.txt", "old")]);
+        let target = dir.path().join("target.txt");
+
+        let error = restore_backup(&target).unwrap_err();
+        assert_eq!(error.code(), "file.backup.missing");
+        assert_eq!(error.path(), target);
+
+        let replacement = dir.path().join("missing.txt");
+        let error = replace_with_backup(&target, &replacement, false).unwrap_err();
+        assert_eq!(error.code(), "file.not.found");
+        assert_eq!(error.path(), replacement);
+        assert_eq!(read(&target), "old");
+    }
+}
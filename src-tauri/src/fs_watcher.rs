@@ -0,0 +1,388 @@
+use log::{error, warn};
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{
+        event::{ModifyKind, RenameMode},
+        EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+    },
+    DebounceEventResult, DebouncedEvent, Debouncer, FileIdMap,
+};
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+use tauri::{
+    plugin::{Builder, TauriPlugin},
+    scope::GlobPattern,
+    AppHandle, Manager, RunEvent, Runtime, Window, WindowEvent,
+};
+
+use crate::{
+    constants::{FS_WATCH_DEFAULT_DEBOUNCE, FS_WATCH_EVENT, PATH_MATCH_OPTIONS},
+    utils::{
+        get_allowed_path_with_string_error, get_page_start_script, get_state_mutex_from_handle,
+    },
+};
+
+/// STATE OBJECT ///
+
+struct FolderWatch {
+    owner: String,
+    // stops watching on drop
+    _debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+}
+
+struct FsWatcherState {
+    watches: HashMap<u64, FolderWatch>,
+    next_id: u64,
+}
+
+impl FsWatcherState {
+    fn new() -> FsWatcherState {
+        FsWatcherState {
+            watches: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    fn reserve_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn remove_owned_by(&mut self, owner: &str) -> usize {
+        let count = self.watches.len();
+        self.watches.retain(|_, watch| watch.owner != owner);
+        count - self.watches.len()
+    }
+}
+
+fn get_fs_watcher_state<R: Runtime>(app_handle: &AppHandle<R>) -> MutexGuard<FsWatcherState> {
+    get_state_mutex_from_handle::<R, FsWatcherState>(app_handle)
+}
+
+/// EVENTS ///
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct WatchOptions {
+    /// Only reports paths matching one of the globs, relative to the watched folder.
+    include: Vec<String>,
+    /// Ignores paths matching one of the globs, like "**/*.log".
+    exclude: Vec<String>,
+    /// Only watches the folder itself, not its sub folders.
+    non_recursive: bool,
+    /// Changes are collected until no new ones arrived for this time.
+    debounce_ms: Option<u64>,
+}
+
+/// A change of the watched folder, paths are absolute and use slashes.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum FsChange {
+    Created { path: String },
+    Modified { path: String },
+    Removed { path: String },
+    Renamed { from: String, to: String },
+}
+
+#[derive(Serialize, Clone)]
+struct FsWatchEvent {
+    id: u64,
+    changes: Vec<FsChange>,
+}
+
+struct ChangeFilter {
+    folder: PathBuf,
+    include: Vec<GlobPattern>,
+    exclude: Vec<GlobPattern>,
+}
+
+impl ChangeFilter {
+    fn new(folder: &Path, options: &WatchOptions) -> Result<ChangeFilter, String> {
+        let to_globs = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| GlobPattern::new(pattern).map_err(|err| err.to_string()))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ChangeFilter {
+            folder: folder.to_path_buf(),
+            include: to_globs(&options.include)?,
+            exclude: to_globs(&options.exclude)?,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let relative_path = match path.strip_prefix(&self.folder) {
+            Ok(relative_path) => relative_path.to_slash_lossy(),
+            Err(_) => return false,
+        };
+        let matches_any = |globs: &[GlobPattern]| {
+            globs
+                .iter()
+                .any(|glob| glob.matches_with(&relative_path, PATH_MATCH_OPTIONS))
+        };
+        (self.include.is_empty() || matches_any(&self.include)) && !matches_any(&self.exclude)
+    }
+
+    // renames are kept if one of the paths matches, so moving a file out of the filter is still noticed
+    fn to_change(&self, event: &DebouncedEvent) -> Option<FsChange> {
+        let first_path = event.paths.first()?;
+        let to_slash = |path: &Path| path.to_slash_lossy().to_string();
+        if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+            let second_path = event.paths.get(1)?;
+            if !self.matches(first_path) && !self.matches(second_path) {
+                return None;
+            }
+            return Some(FsChange::Renamed {
+                from: to_slash(first_path),
+                to: to_slash(second_path),
+            });
+        }
+        if !self.matches(first_path) {
+            return None;
+        }
+        let path = to_slash(first_path);
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                Some(FsChange::Created { path })
+            }
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                Some(FsChange::Removed { path })
+            }
+            EventKind::Modify(_) | EventKind::Any => Some(FsChange::Modified { path }),
+            EventKind::Access(_) | EventKind::Other => None,
+        }
+    }
+}
+
+// like listing folders, every path is checked, so denied sub folders of the watched folder stay hidden
+// renames with a denied side are reported as removal or creation of the allowed path
+fn restrict_to_scope<F: Fn(&Path) -> bool>(change: FsChange, is_allowed: F) -> Option<FsChange> {
+    let is_allowed = |path: &str| is_allowed(Path::new(path));
+    match change {
+        FsChange::Renamed { from, to } => match (is_allowed(&from), is_allowed(&to)) {
+            (true, true) => Some(FsChange::Renamed { from, to }),
+            (true, false) => Some(FsChange::Removed { path: from }),
+            (false, true) => Some(FsChange::Created { path: to }),
+            (false, false) => None,
+        },
+        FsChange::Created { ref path }
+        | FsChange::Modified { ref path }
+        | FsChange::Removed { ref path } => is_allowed(path).then_some(change),
+    }
+}
+
+fn emit_changes<R: Runtime>(
+    window: &Window<R>,
+    id: u64,
+    filter: &ChangeFilter,
+    result: DebounceEventResult,
+) {
+    let events = match result {
+        Ok(events) => events,
+        Err(errors) => {
+            for err in errors {
+                warn!(
+                    "Error while watching '{}': {}",
+                    filter.folder.display(),
+                    err
+                );
+            }
+            return;
+        }
+    };
+    // the scope might have changed since the watch started
+    let fs_scope = window.fs_scope();
+    let mut changes: Vec<FsChange> = Vec::new();
+    for change in events
+        .iter()
+        .filter_map(|event| filter.to_change(event))
+        .filter_map(|change| restrict_to_scope(change, |path| fs_scope.is_allowed(path)))
+    {
+        if !changes.contains(&change) {
+            changes.push(change);
+        }
+    }
+    if changes.is_empty() {
+        return;
+    }
+    if let Err(err) = window.emit(FS_WATCH_EVENT, FsWatchEvent { id, changes }) {
+        error!("Failed to emit file watch event: {}", err);
+    }
+}
+
+/// COMMANDS ///
+
+// reports changes of the folder to the calling window, until unwatched or the window reloads
+#[tauri::command]
+async fn watch_folder<R: Runtime>(
+    app_handle: AppHandle<R>,
+    window: Window<R>,
+    path: &str,
+    options: Option<WatchOptions>,
+) -> Result<u64, String> {
+    let folder = get_allowed_path_with_string_error(&app_handle, path)?;
+    let folder = dunce::canonicalize(folder).map_err(|err| err.to_string())?;
+    if !folder.is_dir() {
+        return Err(String::from("fs.watch.folder.missing"));
+    }
+    let options = options.unwrap_or_default();
+    let filter = ChangeFilter::new(&folder, &options)?;
+    let recursive_mode = if options.non_recursive {
+        RecursiveMode::NonRecursive
+    } else {
+        RecursiveMode::Recursive
+    };
+    let debounce = options
+        .debounce_ms
+        .map_or(FS_WATCH_DEFAULT_DEBOUNCE, Duration::from_millis);
+
+    // the id is reserved first, since the handler needs it
+    // the state is not locked meanwhile, since watching large folders takes a while
+    let id = get_fs_watcher_state(&app_handle).reserve_id();
+    let event_window = window.clone();
+    let mut debouncer = new_debouncer(debounce, None, move |result: DebounceEventResult| {
+        emit_changes(&event_window, id, &filter, result);
+    })
+    .map_err(|err| err.to_string())?;
+    debouncer
+        .watcher()
+        .watch(&folder, recursive_mode)
+        .map_err(|err| err.to_string())?;
+    debouncer.cache().add_root(&folder, recursive_mode);
+
+    get_fs_watcher_state(&app_handle).watches.insert(
+        id,
+        FolderWatch {
+            owner: window.label().to_string(),
+            _debouncer: debouncer,
+        },
+    );
+    Ok(id)
+}
+
+#[tauri::command]
+async fn unwatch_folder<R: Runtime>(app_handle: AppHandle<R>, id: u64) -> Result<(), String> {
+    match get_fs_watcher_state(&app_handle).watches.remove(&id) {
+        Some(_) => Ok(()),
+        None => Err(String::from("fs.watch.id.missing")),
+    }
+}
+
+/// INIT ///
+
+// a reload keeps the window, but the frontend lost all its subscriptions
+fn free_window_watches<R: Runtime>(app_handle: &AppHandle<R>, label: &str) {
+    let count = get_fs_watcher_state(app_handle).remove_owned_by(label);
+    if count > 0 {
+        warn!(
+            "Stopping {} folder watches left open by window '{}'.",
+            count, label
+        );
+    }
+}
+
+const PLUGIN_NAME: &str = "tauri-plugin-ucp-fs-watcher";
+
+// sync, so it is handled before any command of the new page, see the init script
+#[tauri::command]
+fn page_started<R: Runtime>(window: Window<R>) {
+    free_window_watches(&window.app_handle(), window.label())
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new(PLUGIN_NAME)
+        .js_init_script(get_page_start_script(PLUGIN_NAME, "page_started"))
+        .invoke_handler(tauri::generate_handler![
+            page_started,
+            watch_folder,
+            unwatch_folder
+        ])
+        .setup(|app_handle| {
+            app_handle.manage::<Mutex<FsWatcherState>>(Mutex::new(FsWatcherState::new()));
+            Ok(())
+        })
+        .on_event(|app_handle: &AppHandle<R>, event| {
+            if let RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Destroyed,
+                ..
+            } = event
+            {
+                free_window_watches(app_handle, label)
+            }
+        })
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_allowed(path: &Path) -> bool {
+        !path.starts_with("/game/denied")
+    }
+
+    fn created(path: &str) -> FsChange {
+        FsChange::Created {
+            path: String::from(path),
+        }
+    }
+
+    fn removed(path: &str) -> FsChange {
+        FsChange::Removed {
+            path: String::from(path),
+        }
+    }
+
+    fn renamed(from: &str, to: &str) -> FsChange {
+        FsChange::Renamed {
+            from: String::from(from),
+            to: String::from(to),
+        }
+    }
+
+    #[test]
+    fn drops_changes_of_denied_paths() {
+        assert_eq!(
+            restrict_to_scope(created("/game/a.txt"), is_allowed),
+            Some(created("/game/a.txt"))
+        );
+        assert_eq!(
+            restrict_to_scope(created("/game/denied/a.txt"), is_allowed),
+            None
+        );
+        assert_eq!(
+            restrict_to_scope(removed("/game/denied/a.txt"), is_allowed),
+            None
+        );
+    }
+
+    #[test]
+    fn hides_the_denied_side_of_renames() {
+        let change = renamed("/game/a.txt", "/game/b.txt");
+        assert_eq!(restrict_to_scope(change.clone(), is_allowed), Some(change));
+        assert_eq!(
+            restrict_to_scope(renamed("/game/a.txt", "/game/denied/a.txt"), is_allowed),
+            Some(removed("/game/a.txt"))
+        );
+        assert_eq!(
+            restrict_to_scope(renamed("/game/denied/a.txt", "/game/a.txt"), is_allowed),
+            Some(created("/game/a.txt"))
+        );
+        assert_eq!(
+            restrict_to_scope(
+                renamed("/game/denied/a.txt", "/game/denied/b.txt"),
+                is_allowed
+            ),
+            None
+        );
+    }
+}
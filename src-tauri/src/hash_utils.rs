@@ -1,5 +1,8 @@
 use std::fs;
 use std::io;
+use std::path::Path;
+
+use path_slash::PathExt;
 
 use sha2::Digest;
 use sha2::Sha256;
@@ -12,13 +15,51 @@ use crate::utils::get_allowed_path_with_string_error;
 pub async fn get_sha256_of_file(app_handle: AppHandle, path: &str) -> Result<String, String> {
     let source_path = get_allowed_path_with_string_error(&app_handle, path)?;
 
-    let hash_result = || -> Result<String, io::Error> {
-        // source: https://github.com/RustCrypto/hashes
-        let mut file = fs::File::open(&source_path)?;
-        let mut hasher = Sha256::new();
-        io::copy(&mut file, &mut hasher)?;
-        let hash_bytes = hasher.finalize();
-        Ok(format!("{:x}", hash_bytes))
-    }();
-    hash_result.map_err(|error| error.to_string())
+    get_sha256_of_path(source_path).map_err(|error| error.to_string())
+}
+
+pub fn get_sha256_of_path(path: &Path) -> Result<String, io::Error> {
+    // source: https://github.com/RustCrypto/hashes
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    let hash_bytes = hasher.finalize();
+    Ok(format!("{:x}", hash_bytes))
+}
+
+// hashes lines like "<sha256 of file>  <relative path>", sorted by path, like the output of sha256sum
+// symlinks are ignored, so only the content of the folder itself counts
+pub fn get_sha256_of_folder(path: &Path) -> Result<String, io::Error> {
+    let mut files = Vec::new();
+    collect_folder_files(path, path, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for relative_path in files {
+        let file_hash = get_sha256_of_path(&path.join(&relative_path))?;
+        hasher.update(format!("{}  {}\n", file_hash, relative_path));
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Collects the paths of all files in the folder and its sub folders, relative to the base and with slashes.
+/// Symlinks are skipped.
+pub fn collect_folder_files(
+    base: &Path,
+    folder: &Path,
+    files: &mut Vec<String>,
+) -> Result<(), io::Error> {
+    for dir_entry in fs::read_dir(folder)? {
+        let dir_entry = dir_entry?;
+        let file_type = dir_entry.file_type()?;
+        let path = dir_entry.path();
+        if file_type.is_dir() {
+            collect_folder_files(base, &path, files)?;
+        } else if file_type.is_file() {
+            if let Some(relative_path) = path.strip_prefix(base).ok().and_then(|p| p.to_slash()) {
+                files.push(relative_path.to_string());
+            }
+        }
+    }
+    Ok(())
 }
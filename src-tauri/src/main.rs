@@ -4,10 +4,13 @@
 )]
 
 mod constants;
+mod extension_support;
 mod file_support;
+mod fs_watcher;
 mod gui_config;
 mod hash_utils;
 mod logging;
+mod process_support;
 mod run_program;
 mod utils;
 mod zip_support;
@@ -17,14 +20,24 @@ fn main() {
         .plugin(logging::init()) // logging is loaded first, currently using INFO until set by config
         .plugin(gui_config::init())
         .plugin(zip_support::init())
+        .plugin(fs_watcher::init())
         .plugin(tauri_plugin_upload::init())
         .invoke_handler(tauri::generate_handler![
             hash_utils::get_sha256_of_file,
             run_program::os_open_program,
+            process_support::get_running_game_processes,
             file_support::slashify,
             file_support::canonicalize,
             file_support::read_and_filter_dir,
-            file_support::scan_file_for_bytes
+            file_support::read_and_filter_dir_entries,
+            file_support::scan_file_for_bytes,
+            file_support::scan_file_for_patterns,
+            file_support::get_pe_info,
+            file_support::write_file_atomic,
+            file_support::write_text_file_atomic,
+            file_support::replace_file_safely,
+            file_support::restore_file_backup,
+            extension_support::get_extension_infos
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,121 @@
+use path_slash::PathExt;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use sysinfo::{Process, ProcessRefreshKind, System, UpdateKind};
+use tauri::AppHandle;
+
+use crate::{constants::GAME_EXECUTABLES, utils::get_allowed_path_with_string_error};
+
+/// A running game executable of the checked folder.
+#[derive(Serialize)]
+pub struct GameProcess {
+    pub pid: u32,
+    pub name: String,
+    /// None if the process could not be inspected, like elevated processes on windows.
+    pub path: Option<String>,
+    /// Seconds since the unix epoch.
+    pub start_time: u64,
+    /// Runs through Wine or Proton, so the process is the wine loader.
+    pub wine: bool,
+}
+
+fn is_game_executable_name(name: &str) -> bool {
+    GAME_EXECUTABLES
+        .iter()
+        .any(|executable| executable.eq_ignore_ascii_case(name))
+}
+
+// arguments of wine processes use windows paths
+fn get_file_name(path: &str) -> &str {
+    path.rsplit(['\\', '/']).next().unwrap_or(path)
+}
+
+fn is_game_path(path: &Path, game_executables: &[PathBuf]) -> bool {
+    dunce::canonicalize(path).is_ok_and(|path| game_executables.contains(&path))
+}
+
+// wine maps the root of the linux file system to drive Z:, other drives are only found through the working directory
+fn get_wine_candidates(process: &Process, argument: &str) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::from(argument)];
+    if let Some(unix_path) = argument
+        .strip_prefix("Z:")
+        .or_else(|| argument.strip_prefix("z:"))
+    {
+        candidates.push(PathBuf::from(unix_path.replace('\\', "/")));
+    }
+    if let Some(cwd) = process.cwd() {
+        candidates.push(cwd.join(get_file_name(argument)));
+    }
+    candidates
+}
+
+// returns the found executable and if it was started through wine
+fn find_game_executable(
+    process: &Process,
+    game_executables: &[PathBuf],
+) -> Option<(Option<PathBuf>, bool)> {
+    if let Some(exe) = process.exe() {
+        let is_game_name = exe
+            .file_name()
+            .is_some_and(|name| is_game_executable_name(&name.to_string_lossy()));
+        if is_game_name && is_game_path(exe, game_executables) {
+            return Some((Some(exe.to_path_buf()), false));
+        }
+    }
+
+    let wine_argument = process
+        .cmd()
+        .iter()
+        .find(|argument| is_game_executable_name(get_file_name(argument)));
+    if let Some(argument) = wine_argument {
+        return get_wine_candidates(process, argument)
+            .into_iter()
+            .find(|candidate| is_game_path(candidate, game_executables))
+            .map(|candidate| (Some(candidate), true));
+    }
+
+    // without access the location is unknown, so it counts as running to stay on the safe side
+    let is_hidden = process.exe().is_none() && process.cmd().is_empty();
+    (is_hidden && is_game_executable_name(process.name())).then_some((None, false))
+}
+
+// async (other thread), since listing all processes takes a moment
+// finds running game executables of the folder, also if they are started through wine or proton
+#[tauri::command]
+pub async fn get_running_game_processes(
+    app_handle: AppHandle,
+    game_folder: &str,
+) -> Result<Vec<GameProcess>, String> {
+    let folder = get_allowed_path_with_string_error(&app_handle, game_folder)?;
+    let folder = dunce::canonicalize(folder).map_err(|err| err.to_string())?;
+    let game_executables: Vec<PathBuf> = GAME_EXECUTABLES
+        .iter()
+        .filter_map(|executable| dunce::canonicalize(folder.join(executable)).ok())
+        .collect();
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessRefreshKind::new()
+            .with_exe(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_cwd(UpdateKind::OnlyIfNotSet),
+    );
+
+    let mut game_processes: Vec<GameProcess> = system
+        .processes()
+        .values()
+        .filter(|process| process.thread_kind().is_none()) // linux also lists every thread
+        .filter_map(|process| {
+            let (path, wine) = find_game_executable(process, &game_executables)?;
+            Some(GameProcess {
+                pid: process.pid().as_u32(),
+                name: process.name().to_string(),
+                path: path.map(|path| path.to_slash_lossy().to_string()),
+                start_time: process.start_time(),
+                wine,
+            })
+        })
+        .collect();
+    game_processes.sort_by_key(|game_process| game_process.start_time);
+    Ok(game_processes)
+}
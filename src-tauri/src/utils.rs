@@ -62,6 +62,13 @@ pub fn get_roaming_folder_path() -> PathBuf {
     data_path
 }
 
+// appends to the file name, unlike "with_extension", which replaces an existing extension
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut suffixed_path = path.as_os_str().to_owned();
+    suffixed_path.push(suffix);
+    PathBuf::from(suffixed_path)
+}
+
 pub fn get_allowed_path<'a, R: Runtime>(
     app_handle: &AppHandle<R>,
     path: &'a str,
@@ -74,6 +81,18 @@ pub fn get_allowed_path<'a, R: Runtime>(
     }
 }
 
+// init scripts run before any script of the page and the ipc keeps the order of the messages,
+// so a sync command called here is handled before any command of the new page
+// frames are ignored, since they share the window with the actual page
+pub fn get_page_start_script(plugin_name: &str, command: &str) -> String {
+    format!(
+        "if (window === window.top && window.__TAURI_INVOKE__ !== undefined) {{ \
+            window.__TAURI_INVOKE__('plugin:{}|{}').catch(() => {{}}); \
+        }}",
+        plugin_name, command
+    )
+}
+
 pub fn get_allowed_path_with_string_error<'a, R: Runtime>(
     app_handle: &AppHandle<R>,
     path: &'a str,
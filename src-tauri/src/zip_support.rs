@@ -1,46 +1,146 @@
+use log::{error, warn};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{self, File},
-    io::{self, BufReader, BufWriter, Read, Write},
-    path::Path,
-    pin::Pin,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
     sync::Mutex,
     sync::MutexGuard,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
 };
 use tauri::{
     plugin::{Builder, TauriPlugin},
     scope::GlobPattern,
-    AppHandle, Manager, Runtime,
+    AppHandle, Manager, RunEvent, Runtime, Window, WindowEvent,
 };
 use zip::{result::ZipError, write::FileOptions, ZipArchive, ZipWriter};
 
 use crate::{
-    constants::PATH_MATCH_OPTIONS,
-    utils::{get_allowed_path_with_string_error, get_state_mutex_from_handle},
+    constants::{
+        PATH_MATCH_OPTIONS, ZIP_EXTRACT_PROGRESS_EVENT, ZIP_HANDLE_IDLE_TIMEOUT,
+        ZIP_HANDLE_SWEEP_INTERVAL, ZIP_MEMORY_READER_MAX_BYTES, ZIP_PACK_PROGRESS_EVENT,
+    },
+    hash_utils::get_sha256_of_path,
+    utils::{
+        get_allowed_path, get_allowed_path_with_string_error, get_page_start_script,
+        get_state_mutex_from_handle, with_suffix,
+    },
 };
 
+pub mod archive;
+mod diff;
+mod entry_stream;
+mod error;
+mod extraction;
+mod handle_registry;
+mod limits;
+mod manifest;
+mod pack;
+mod safety;
+mod verify;
+mod write_options;
+
+use archive::{ArchiveEntry, ArchiveReader, ArchiveSource};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use diff::{ArchiveDiff, DiffOptions, DiffSide};
+use entry_stream::ZipEntryStream;
+use error::ZipSupportError;
+use extraction::{ExtractionOptions, ExtractionProgress};
+use handle_registry::HandleRegistry;
+use limits::ZipLimits;
+use manifest::{UninstallOptions, UninstallResult};
+use pack::{PackOptions, PackProgress};
+use verify::ZipProblem;
+use write_options::{ZipEntryOptions, ZipWriterOptions};
+
 /// STATE OBJECT ///
 
+// every archive has its own lock, the state lock is only held to get them
+type SharedZipReader = Arc<Mutex<ZipReaderHelper>>;
+type SharedZipWriter = Arc<Mutex<ZipWriterHelper>>;
+
 struct ZipCollectionsState {
-    reader: HashMap<usize, Pin<Box<ZipReaderHelper>>>,
-    writer: HashMap<usize, Pin<Box<ZipWriterHelper>>>,
+    reader: HandleRegistry<SharedZipReader>,
+    writer: HandleRegistry<SharedZipWriter>,
+    entry_stream: HandleRegistry<Arc<ZipEntryStream>>,
+}
+
+/// Handles taken out of the state, to be closed without holding the lock.
+struct ZipHandleLeftovers {
+    readers: Vec<SharedZipReader>,
+    writers: Vec<SharedZipWriter>,
+    entry_streams: Vec<Arc<ZipEntryStream>>,
 }
 
 impl ZipCollectionsState {
     fn new() -> ZipCollectionsState {
         ZipCollectionsState {
-            reader: HashMap::new(),
-            writer: HashMap::new(),
+            reader: HandleRegistry::new(),
+            writer: HandleRegistry::new(),
+            entry_stream: HandleRegistry::new(),
         }
     }
 
-    fn get_readers(&mut self) -> &mut HashMap<usize, Pin<Box<ZipReaderHelper>>> {
+    fn get_readers(&mut self) -> &mut HandleRegistry<SharedZipReader> {
         &mut self.reader
     }
 
-    fn get_writers(&mut self) -> &mut HashMap<usize, Pin<Box<ZipWriterHelper>>> {
+    fn get_writers(&mut self) -> &mut HandleRegistry<SharedZipWriter> {
         &mut self.writer
     }
+
+    fn get_entry_streams(&mut self) -> &mut HandleRegistry<Arc<ZipEntryStream>> {
+        &mut self.entry_stream
+    }
+
+    fn remove_owned_by(&mut self, owner: &str) -> ZipHandleLeftovers {
+        ZipHandleLeftovers {
+            readers: self.reader.remove_owned_by(owner),
+            writers: self.writer.remove_owned_by(owner),
+            entry_streams: self.entry_stream.remove_owned_by(owner),
+        }
+    }
+
+    fn remove_idle(&mut self) -> ZipHandleLeftovers {
+        ZipHandleLeftovers {
+            readers: self.reader.remove_idle(ZIP_HANDLE_IDLE_TIMEOUT),
+            writers: self.writer.remove_idle(ZIP_HANDLE_IDLE_TIMEOUT),
+            entry_streams: self.entry_stream.remove_idle(ZIP_HANDLE_IDLE_TIMEOUT),
+        }
+    }
+}
+
+impl ZipHandleLeftovers {
+    fn count(&self) -> usize {
+        self.readers.len() + self.writers.len() + self.entry_streams.len()
+    }
+
+    // readers and streams only need to be dropped, but writers need to write their central directory
+    // waits for running writes, since the writer lock is taken
+    fn close(self) {
+        for writer in self.writers {
+            if let Err(err) = writer.lock().unwrap().finish() {
+                error!("Failed to finish left over zip writer: {}", err);
+            }
+        }
+    }
+}
+
+/// Cancel flags of running extractions and packs, registered by job id.
+struct ZipExtractionJobs {
+    jobs: HashMap<String, Arc<AtomicBool>>,
+}
+
+impl ZipExtractionJobs {
+    fn new() -> ZipExtractionJobs {
+        ZipExtractionJobs {
+            jobs: HashMap::new(),
+        }
+    }
 }
 
 /// HELPER ///
@@ -51,256 +151,555 @@ fn get_zip_collections_state<R: Runtime>(
     get_state_mutex_from_handle::<R, ZipCollectionsState>(&app_handle)
 }
 
-fn get_pin_box_mem_address<S>(obj: &Pin<Box<S>>) -> usize {
-    &**obj as *const S as usize
-}
-
-fn do_with_reader<R: Runtime, T, F: FnOnce(&mut Pin<Box<ZipReaderHelper>>) -> Result<T, String>>(
+// only the lock of the used archive is held while running, so other archives can be used in parallel
+fn do_with_reader<R: Runtime, T, F: FnOnce(&mut ZipReaderHelper) -> Result<T, String>>(
     app_handle: &AppHandle<R>,
-    id: usize,
+    id: u64,
     func: F,
 ) -> Result<T, String> {
-    let mut state = get_zip_collections_state(&app_handle);
-    ZipReaderHelper::get_reader(&mut state, id).and_then(func)
+    let reader = ZipReaderHelper::get_reader(app_handle, id)?;
+    let mut reader = reader.lock().unwrap();
+    func(&mut reader)
 }
 
-fn do_with_writer<R: Runtime, T, F: FnOnce(&mut Pin<Box<ZipWriterHelper>>) -> Result<T, String>>(
+fn do_with_writer<R: Runtime, T, F: FnOnce(&mut ZipWriterHelper) -> Result<T, String>>(
     app_handle: &AppHandle<R>,
-    id: usize,
+    id: u64,
     func: F,
 ) -> Result<T, String> {
-    let mut state: MutexGuard<'_, ZipCollectionsState> = get_zip_collections_state(&app_handle);
-    ZipWriterHelper::get_writer(&mut state, id).and_then(func)
+    let writer = ZipWriterHelper::get_writer(app_handle, id)?;
+    let mut writer = writer.lock().unwrap();
+    func(&mut writer)
+}
+
+// a reload keeps the window, but the frontend lost all its handles
+// called on the main thread, so the handles are only removed here and closed on another thread,
+// since finishing left over writers might take a while
+fn free_window_handles<R: Runtime>(app_handle: &AppHandle<R>, label: &str) {
+    let leftovers = get_zip_collections_state(app_handle).remove_owned_by(label);
+    if leftovers.count() == 0 {
+        return;
+    }
+    warn!(
+        "Closing {} zip handles left open by window '{}'.",
+        leftovers.count(),
+        label
+    );
+    thread::spawn(move || leftovers.close());
+}
+
+fn free_idle_handles<R: Runtime>(app_handle: &AppHandle<R>) {
+    let leftovers = get_zip_collections_state(app_handle).remove_idle();
+    if leftovers.count() > 0 {
+        warn!("Closing {} idle zip handles.", leftovers.count());
+    }
+    leftovers.close();
 }
 
 /// READER ///
 
+// zip, tar.gz and 7z archives are all read through the same reader
 struct ZipReaderHelper {
-    id: usize,
-    reader: ZipArchive<BufReader<File>>,
+    source: ArchiveSource,
+    reader: ArchiveReader,
 }
 
 impl ZipReaderHelper {
     fn register_reader<R: Runtime>(
-        app_handle: &AppHandle<R>,
-        reader: ZipArchive<BufReader<File>>,
-    ) -> usize {
+        window: &Window<R>,
+        source: ArchiveSource,
+        reader: ArchiveReader,
+    ) -> Result<u64, String> {
         let helper = ZipReaderHelper {
-            id: 0,
+            source,
             reader: reader,
         };
-        let mut boxed_helper = Box::pin(helper);
-
-        let address_id = get_pin_box_mem_address(&boxed_helper);
-        boxed_helper.id = address_id;
-
-        get_zip_collections_state(app_handle)
+        get_zip_collections_state(&window.app_handle())
             .get_readers()
-            .insert(address_id, boxed_helper);
-
-        address_id
+            .insert(window.label(), Arc::new(Mutex::new(helper)))
+            .ok_or(String::from("zip.id.limit.reached"))
     }
 
-    fn free_reader<R: Runtime>(app_handle: &AppHandle<R>, id: usize) -> Result<(), String> {
+    fn free_reader<R: Runtime>(app_handle: &AppHandle<R>, id: u64) -> Result<(), String> {
         match get_zip_collections_state(app_handle)
             .get_readers()
-            .remove(&id)
+            .remove(id)
         {
             Some(_) => Ok(()),
             None => Err(String::from("zip.id.missing")),
         }
     }
 
-    // can not take app handle, since handle mutex needs to be kept in outer scope
-    fn get_reader(
-        state: &mut ZipCollectionsState,
-        id: usize,
-    ) -> Result<&mut Pin<Box<ZipReaderHelper>>, String> {
-        state
+    fn get_reader<R: Runtime>(
+        app_handle: &AppHandle<R>,
+        id: u64,
+    ) -> Result<SharedZipReader, String> {
+        get_zip_collections_state(app_handle)
             .get_readers()
-            .get_mut(&id)
+            .get_mut(id)
+            .cloned()
             .ok_or(String::from("zip.id.missing"))
     }
 
     fn is_empty(&self) -> bool {
-        self.reader.is_empty()
+        self.reader.entries().is_empty()
     }
 
     fn get_number_of_entries(&self) -> usize {
-        self.reader.len()
+        self.reader.entries().len()
     }
 
     fn exist(&mut self, path: &str) -> bool {
-        match self.reader.by_name(path) {
-            Ok(_) => true,
-            Err(_) => false,
-        }
+        self.reader.find_entry(path).is_some()
     }
 
     fn get_entry_names(&mut self, pattern: &str) -> Result<Vec<String>, String> {
+        let names = self
+            .reader
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str());
         if pattern.is_empty() {
-            return Ok(self.reader.file_names().map(String::from).collect());
+            return Ok(names.map(String::from).collect());
+        }
+        let glob_pattern = GlobPattern::new(pattern).map_err(|err| err.to_string())?;
+        Ok(names
+            .filter(|name| glob_pattern.matches_with(name, PATH_MATCH_OPTIONS))
+            .map(String::from)
+            .collect())
+    }
+
+    // the entries were listed when the archive was opened, so nothing is decompressed
+    fn get_entry_metadata(&mut self, pattern: &str) -> Result<Vec<ArchiveEntry>, String> {
+        if pattern.is_empty() {
+            return Ok(self.reader.entries().to_vec());
         }
         let glob_pattern = GlobPattern::new(pattern).map_err(|err| err.to_string())?;
         Ok(self
             .reader
-            .file_names()
-            .filter(|file_name| glob_pattern.matches_with(file_name, PATH_MATCH_OPTIONS))
-            .map(String::from)
+            .entries()
+            .iter()
+            .filter(|entry| glob_pattern.matches_with(&entry.name, PATH_MATCH_OPTIONS))
+            .cloned()
             .collect())
     }
 
+    // nested archives can not be read without decompressing them, so they are kept in memory
+    fn get_entry_as_archive_source(
+        &mut self,
+        path: &str,
+    ) -> Result<ArchiveSource, ZipSupportError> {
+        let index = self
+            .reader
+            .find_entry(path)
+            .ok_or(ZipSupportError::Zip(ZipError::FileNotFound))?;
+        let size = self.reader.entries()[index].size;
+        if size > ZIP_MEMORY_READER_MAX_BYTES {
+            return Err(ZipSupportError::Key("zip.memory.size.exceeded"));
+        }
+        let mut bytes = Vec::with_capacity(size as usize);
+        self.reader.read_entry(path, |reader| {
+            reader.read_to_end(&mut bytes)?;
+            Ok(())
+        })?;
+        Ok(ArchiveSource::Memory(bytes.into()))
+    }
+
     fn get_entry_as_binary(&mut self, path: &str) -> Result<Vec<u8>, String> {
-        let read_result = || -> Result<Vec<u8>, ZipError> {
-            let mut zip_file = self.reader.by_name(path)?;
+        let mut vec_buf: Vec<u8> = Vec::new();
+        self.reader
+            .read_entry(path, |reader| {
+                reader.read_to_end(&mut vec_buf)?;
+                Ok(())
+            })
+            .map_err(|error| error.into_string())?;
+        Ok(vec_buf)
+    }
 
-            let mut vec_buf: Vec<u8> = Vec::new();
-            zip_file.read_to_end(&mut vec_buf)?;
-            Ok(vec_buf)
-        }();
-        read_result.map_err(|error| error.to_string())
+    fn open_entry_stream(&mut self, path: &str) -> Result<ZipEntryStream, String> {
+        ZipEntryStream::open(&self.source, path).map_err(|err| err.into_string())
     }
 
-    fn get_entry_as_text(&mut self, path: &str) -> Result<String, String> {
-        let read_result = || -> Result<String, ZipError> {
-            let mut zip_file = self.reader.by_name(path)?;
+    // streams the entry into the file, without holding it in memory
+    fn extract_entry_to_file(&mut self, path: &str, dest_path: &Path) -> Result<(), String> {
+        self.reader
+            .read_entry(path, |reader| {
+                let mut buf_writer = BufWriter::new(File::create(dest_path)?);
+                io::copy(reader, &mut buf_writer)?;
+                buf_writer.flush()?;
+                Ok(())
+            })
+            .map_err(|error| error.into_string())
+    }
 
-            let mut string: String = String::new();
-            zip_file.read_to_string(&mut string)?;
-            Ok(string)
-        }();
-        read_result.map_err(|error| error.to_string())
+    fn get_entry_as_text(&mut self, path: &str) -> Result<String, String> {
+        let mut string: String = String::new();
+        self.reader
+            .read_entry(path, |reader| {
+                reader.read_to_string(&mut string)?;
+                Ok(())
+            })
+            .map_err(|error| error.into_string())?;
+        Ok(string)
     }
 }
 
 /// WRITER ///
 
+const WRITER_STAGING_SUFFIX: &str = ".ucp-writer-staging";
+const WRITER_SORTED_SUFFIX: &str = ".ucp-writer-sorted";
+
 struct ZipWriterHelper {
-    id: usize,
     writer: ZipWriter<BufWriter<File>>,
+    options: ZipWriterOptions,
+    // archives are written to a staging file next to the target first,
+    // so an existing target stays untouched until finishing, and reproducible entries can be sorted
+    target: PathBuf,
+    path: PathBuf,
+    // the archive that is updated, its kept entries are copied when finishing
+    update_source: Option<ZipArchive<BufReader<File>>>,
+    deleted_entries: Vec<String>,
+    written_entries: HashSet<String>,
 }
 
 impl ZipWriterHelper {
-    fn register_writer<R: Runtime>(
-        app_handle: &AppHandle<R>,
-        writer: ZipWriter<BufWriter<File>>,
-    ) -> usize {
-        let helper = ZipWriterHelper {
-            id: 0,
-            writer: writer,
+    fn create(target: &Path, options: ZipWriterOptions) -> Result<ZipWriterHelper, io::Error> {
+        let update_source = if options.update && target.exists() {
+            Some(ZipArchive::new(BufReader::new(File::open(target)?))?)
+        } else {
+            None
         };
-        let mut boxed_helper = Box::pin(helper);
+        let path = with_suffix(target, WRITER_STAGING_SUFFIX);
+        let file = File::create(&path)?;
+        Ok(ZipWriterHelper {
+            writer: ZipWriter::new(BufWriter::new(file)),
+            options,
+            target: target.to_path_buf(),
+            path,
+            update_source,
+            deleted_entries: Vec::new(),
+            written_entries: HashSet::new(),
+        })
+    }
 
-        let address_id = get_pin_box_mem_address(&boxed_helper);
-        boxed_helper.id = address_id;
+    // every written entry goes through here, so it replaces the kept entry of the same name
+    fn start_entry(&mut self, path: &str, file_options: FileOptions) -> Result<(), ZipError> {
+        self.writer.start_file(path, file_options)?;
+        self.written_entries.insert(path.to_string());
+        Ok(())
+    }
 
-        get_zip_collections_state(app_handle)
-            .get_writers()
-            .insert(address_id, boxed_helper);
+    fn start_directory(&mut self, path: &str, file_options: FileOptions) -> Result<(), ZipError> {
+        self.writer.add_directory(path, file_options)?;
+        // the writer adds the slash, if it is missing
+        let name = if path.ends_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/", path)
+        };
+        self.written_entries.insert(name);
+        Ok(())
+    }
 
-        address_id
+    fn register_writer<R: Runtime>(
+        window: &Window<R>,
+        helper: ZipWriterHelper,
+    ) -> Result<u64, String> {
+        get_zip_collections_state(&window.app_handle())
+            .get_writers()
+            .insert(window.label(), Arc::new(Mutex::new(helper)))
+            .ok_or(String::from("zip.id.limit.reached"))
     }
 
-    fn free_writer<R: Runtime>(app_handle: &AppHandle<R>, id: usize) -> Result<(), String> {
-        get_zip_collections_state(app_handle)
+    fn free_writer<R: Runtime>(app_handle: &AppHandle<R>, id: u64) -> Result<(), String> {
+        // removed first, so the state lock is not held while finishing
+        let helper = get_zip_collections_state(app_handle)
             .get_writers()
-            .remove(&id)
-            .ok_or(String::from("zip.id.missing"))?
-            .writer
-            .finish()
-            .map(|mut buf_writer| buf_writer.flush())
-            .map_err(|err| err.to_string())
-            .map(|_| ())
+            .remove(id)
+            .ok_or(String::from("zip.id.missing"))?;
+        let mut helper = helper.lock().unwrap();
+        helper.finish().map_err(|err| err.to_string())
+    }
+
+    fn is_kept(&self, name: &str) -> bool {
+        !self.written_entries.contains(name)
+            && !self.deleted_entries.iter().any(|deleted| {
+                name == deleted || (deleted.ends_with('/') && name.starts_with(deleted.as_str()))
+            })
+    }
+
+    // the original archive is closed afterwards, so it can be replaced
+    fn copy_kept_entries(&mut self) -> Result<(), ZipError> {
+        let mut update_source = match self.update_source.take() {
+            Some(update_source) => update_source,
+            None => return Ok(()),
+        };
+        for index in 0..update_source.len() {
+            let file = update_source.by_index_raw(index)?;
+            if self.is_kept(file.name()) {
+                self.writer.raw_copy_file(file)?;
+            }
+        }
+        Ok(())
     }
 
-    // can not take app handle, since handle mutex needs to be kept in outer scope
-    fn get_writer(
-        state: &mut ZipCollectionsState,
-        id: usize,
-    ) -> Result<&mut Pin<Box<ZipWriterHelper>>, String> {
-        state
+    // the target is only replaced by a rename, so it is never left half written
+    fn finish(&mut self) -> Result<(), ZipError> {
+        let result = self.write_and_replace();
+        // the staging file was either moved or is useless, also if anything failed
+        if self.path.exists() {
+            if let Err(err) = fs::remove_file(&self.path) {
+                error!("Failed to remove zip writer staging file: {}", err);
+            }
+        }
+        result
+    }
+
+    fn write_and_replace(&mut self) -> Result<(), ZipError> {
+        self.copy_kept_entries()?;
+        self.writer.finish()?.flush()?;
+        if self.options.reproducible {
+            copy_sorted(&self.path, &self.target)
+        } else {
+            fs::rename(&self.path, &self.target).map_err(ZipError::from)
+        }
+    }
+
+    // for archives that failed to be written completely
+    // only the staging file is removed, so the target stays untouched
+    fn discard(self) {
+        let path = self.path.clone();
+        drop(self);
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                error!("Failed to remove discarded zip archive: {}", err)
+            }
+            _ => (),
+        }
+    }
+
+    fn get_writer<R: Runtime>(
+        app_handle: &AppHandle<R>,
+        id: u64,
+    ) -> Result<SharedZipWriter, String> {
+        get_zip_collections_state(app_handle)
             .get_writers()
-            .get_mut(&id)
+            .get_mut(id)
+            .cloned()
             .ok_or(String::from("zip.id.missing"))
     }
 
-    fn add_directory(&mut self, path: &str) -> Result<(), String> {
-        self.writer
-            .add_directory(path, FileOptions::default())
+    fn add_directory(
+        &mut self,
+        path: &str,
+        options: Option<&ZipEntryOptions>,
+    ) -> Result<(), String> {
+        let file_options = self
+            .options
+            .to_file_options(options)
+            .map_err(|err| err.to_string())?;
+        self.start_directory(path, file_options)
             .map_err(|err| err.to_string())
     }
 
-    fn write_entry_from_binary(&mut self, path: &str, binary: &[u8]) -> Result<(), String> {
+    // only entries of the updated archive can be deleted, written entries can not be taken back
+    // deleting a folder also deletes everything inside of it
+    fn delete_entry(&mut self, path: &str) -> Result<(), String> {
+        let update_source = self
+            .update_source
+            .as_ref()
+            .ok_or(String::from("zip.writer.update.required"))?;
+        let exists = update_source
+            .file_names()
+            .any(|name| name == path || (path.ends_with('/') && name.starts_with(path)));
+        if !exists {
+            return Err(String::from("zip.writer.entry.missing"));
+        }
+        self.deleted_entries.push(path.to_string());
+        Ok(())
+    }
+
+    fn write_entry_from_binary(
+        &mut self,
+        path: &str,
+        binary: &[u8],
+        options: Option<&ZipEntryOptions>,
+    ) -> Result<(), String> {
+        let file_options = self
+            .options
+            .to_file_options(options)
+            .map_err(|err| err.to_string())?;
         let write_result = || -> Result<(), ZipError> {
-            self.writer.start_file(path, FileOptions::default())?;
+            self.start_entry(path, file_options)?;
             self.writer.write_all(binary)?;
             Ok(())
         }();
         write_result.map_err(|error| error.to_string())
     }
 
-    fn write_entry_from_text(&mut self, path: &str, text: &str) -> Result<(), String> {
-        self.write_entry_from_binary(path, text.as_bytes())
+    fn write_entry_from_text(
+        &mut self,
+        path: &str,
+        text: &str,
+        options: Option<&ZipEntryOptions>,
+    ) -> Result<(), String> {
+        self.write_entry_from_binary(path, text.as_bytes(), options)
     }
 
     // WARNING: There is no same file check, so this might fill until out of space or memory if it points to the same
     // alternative would be an endless loop...
-    fn write_entry_from_file(&mut self, path: &str, source_path: &Path) -> Result<(), String> {
+    fn write_entry_from_file(
+        &mut self,
+        path: &str,
+        source_path: &Path,
+        options: Option<&ZipEntryOptions>,
+    ) -> Result<(), String> {
+        let file_options = self
+            .options
+            .to_file_options(options)
+            .map_err(|err| err.to_string())?;
         let create_result = || -> Result<(), io::Error> {
             let file: File = File::open(source_path)?;
             let mut buf_reader = BufReader::new(file);
-            self.writer.start_file(path, FileOptions::default())?;
+            self.start_entry(path, file_options)?;
             io::copy(&mut buf_reader, &mut self.writer).map(|_| ())
         }();
         create_result.map_err(|error| error.to_string())
     }
 }
 
+// entries are copied without decompressing them again, so only the order changes
+fn copy_sorted(source: &Path, target: &Path) -> Result<(), ZipError> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(source)?))?;
+    let mut names: Vec<String> = archive.file_names().map(String::from).collect();
+    names.sort();
+
+    let sorted_path = with_suffix(target, WRITER_SORTED_SUFFIX);
+    let copy_result = || -> Result<(), ZipError> {
+        let mut writer = ZipWriter::new(BufWriter::new(File::create(&sorted_path)?));
+        for name in names {
+            writer.raw_copy_file(archive.by_name(&name)?)?;
+        }
+        writer.finish()?.flush()?;
+        fs::rename(&sorted_path, target)?;
+        Ok(())
+    }();
+    if copy_result.is_err() {
+        let _ = fs::remove_file(&sorted_path);
+    }
+    copy_result
+}
+
 /// API ///
 
+// reader and writer commands are async (other thread), since they only lock their own archive
+// and should neither block the main thread nor wait for each other
+
+// also opens tar.gz and 7z archives, the format is detected by the content
+// archives with unsafe entry paths or exceeding the limits are rejected
 #[tauri::command]
-fn load_zip_reader<R: Runtime>(app_handle: AppHandle<R>, source: &str) -> Result<usize, String> {
-    let source_path = get_allowed_path_with_string_error(&app_handle, source)?;
-    let create_result = || -> Result<usize, ZipError> {
-        let file = File::open(source_path)?;
-        let buf_reader = BufReader::new(file);
-        let reader = ZipArchive::new(buf_reader)?;
-        Ok(ZipReaderHelper::register_reader(&app_handle, reader))
-    }();
-    create_result.map_err(|error| error.to_string())
+async fn load_zip_reader<R: Runtime>(
+    app_handle: AppHandle<R>,
+    window: Window<R>,
+    source: &str,
+    limits: Option<ZipLimits>,
+) -> Result<u64, ZipSupportError> {
+    let source_path = get_allowed_path(&app_handle, source)?;
+    open_reader(
+        &window,
+        ArchiveSource::File(source_path.to_path_buf()),
+        limits,
+    )
+}
+
+/// Opens an archive from an untrusted source.
+/// Archives with unsafe entry paths or exceeding the limits are rejected, before anything is decompressed.
+pub fn open_checked_archive(
+    source: &ArchiveSource,
+    limits: Option<ZipLimits>,
+) -> Result<ArchiveReader, ZipSupportError> {
+    let mut reader = source.open()?;
+    safety::validate_archive(&mut reader)?;
+    limits::check_archive(&reader, &limits.unwrap_or_default())?;
+    Ok(reader)
+}
+
+fn open_reader<R: Runtime>(
+    window: &Window<R>,
+    source: ArchiveSource,
+    limits: Option<ZipLimits>,
+) -> Result<u64, ZipSupportError> {
+    let reader = open_checked_archive(&source, limits)?;
+    ZipReaderHelper::register_reader(window, source, reader)
+        .map_err(|_| ZipSupportError::Key("zip.id.limit.reached"))
+}
+
+// opens an archive stored as entry of another reader, like plugins inside an extension pack
+// the entry is copied into memory, so the new reader does not depend on the other one
+#[tauri::command]
+async fn load_zip_reader_from_entry<R: Runtime>(
+    app_handle: AppHandle<R>,
+    window: Window<R>,
+    id: u64,
+    path: &str,
+    limits: Option<ZipLimits>,
+) -> Result<u64, ZipSupportError> {
+    let parent_reader = ZipReaderHelper::get_reader(&app_handle, id)
+        .map_err(|_| ZipSupportError::Key("zip.id.missing"))?;
+    let source = parent_reader
+        .lock()
+        .unwrap()
+        .get_entry_as_archive_source(path)?;
+    open_reader(&window, source, limits)
+}
+
+// does not require a path in the fs scope, since the data is already in memory
+#[tauri::command]
+async fn load_zip_reader_from_binary<R: Runtime>(
+    window: Window<R>,
+    binary: Vec<u8>,
+    limits: Option<ZipLimits>,
+) -> Result<u64, ZipSupportError> {
+    if binary.len() as u64 > ZIP_MEMORY_READER_MAX_BYTES {
+        return Err(ZipSupportError::Key("zip.memory.size.exceeded"));
+    }
+    open_reader(&window, ArchiveSource::Memory(binary.into()), limits)
 }
 
 #[tauri::command]
-fn close_zip_reader<R: Runtime>(app_handle: AppHandle<R>, id: usize) -> Result<(), String> {
+async fn close_zip_reader<R: Runtime>(app_handle: AppHandle<R>, id: u64) -> Result<(), String> {
     ZipReaderHelper::free_reader(&app_handle, id)
 }
 
 #[tauri::command]
-fn is_zip_reader_empty<R: Runtime>(app_handle: AppHandle<R>, id: usize) -> Result<bool, String> {
+async fn is_zip_reader_empty<R: Runtime>(
+    app_handle: AppHandle<R>,
+    id: u64,
+) -> Result<bool, String> {
     do_with_reader(&app_handle, id, |reader| Ok(reader.is_empty()))
 }
 
 #[tauri::command]
-fn get_zip_reader_number_of_entries<R: Runtime>(
+async fn get_zip_reader_number_of_entries<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
 ) -> Result<usize, String> {
     do_with_reader(&app_handle, id, |reader| Ok(reader.get_number_of_entries()))
 }
 
 #[tauri::command]
-fn exist_zip_reader_entry<R: Runtime>(
+async fn exist_zip_reader_entry<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
 ) -> Result<bool, String> {
     do_with_reader(&app_handle, id, |reader| Ok(reader.exist(path)))
 }
 
 #[tauri::command]
-fn get_zip_reader_entry_names<R: Runtime>(
+async fn get_zip_reader_entry_names<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     pattern: &str,
 ) -> Result<Vec<String>, String> {
     // no idea how lifetime params could be set to avoid creating the string copies...
@@ -308,129 +707,635 @@ fn get_zip_reader_entry_names<R: Runtime>(
 }
 
 #[tauri::command]
-fn get_zip_reader_entry_as_binary<R: Runtime>(
+async fn get_zip_reader_entry_metadata<R: Runtime>(
+    app_handle: AppHandle<R>,
+    id: u64,
+    pattern: &str,
+) -> Result<Vec<ArchiveEntry>, String> {
+    do_with_reader(&app_handle, id, |reader| reader.get_entry_metadata(pattern))
+}
+
+#[tauri::command]
+async fn get_zip_reader_entry_as_binary<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
 ) -> Result<Vec<u8>, String> {
     do_with_reader(&app_handle, id, |reader| reader.get_entry_as_binary(path))
 }
 
 #[tauri::command]
-fn get_zip_reader_entry_as_text<R: Runtime>(
+async fn get_zip_reader_entry_as_text<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
 ) -> Result<String, String> {
     do_with_reader(&app_handle, id, |reader| reader.get_entry_as_text(path))
 }
 
+// the entry is read in its own thread, so the stream stays valid even if the reader is closed
 #[tauri::command]
-fn load_zip_writer<R: Runtime>(app_handle: AppHandle<R>, source: &str) -> Result<usize, String> {
+async fn open_zip_reader_entry_stream<R: Runtime>(
+    app_handle: AppHandle<R>,
+    window: Window<R>,
+    id: u64,
+    path: &str,
+) -> Result<u64, String> {
+    let stream = do_with_reader(&app_handle, id, |reader| reader.open_entry_stream(path))?;
+    get_zip_collections_state(&app_handle)
+        .get_entry_streams()
+        .insert(window.label(), Arc::new(stream))
+        .ok_or(String::from("zip.id.limit.reached"))
+}
+
+// returns the next chunk as base64 string, or None if the entry was fully read
+// the lock is not held while waiting for the chunk
+#[tauri::command]
+async fn read_zip_reader_entry_stream<R: Runtime>(
+    app_handle: AppHandle<R>,
+    stream_id: u64,
+) -> Result<Option<String>, String> {
+    let stream = get_zip_collections_state(&app_handle)
+        .get_entry_streams()
+        .get_mut(stream_id)
+        .cloned()
+        .ok_or(String::from("zip.stream.id.missing"))?;
+    let chunk = stream.read_chunk()?;
+    Ok(chunk.map(|chunk| BASE64.encode(chunk)))
+}
+
+#[tauri::command]
+fn close_zip_reader_entry_stream<R: Runtime>(
+    app_handle: AppHandle<R>,
+    stream_id: u64,
+) -> Result<(), String> {
+    get_zip_collections_state(&app_handle)
+        .get_entry_streams()
+        .remove(stream_id)
+        .map(|_| ())
+        .ok_or(String::from("zip.stream.id.missing"))
+}
+
+#[tauri::command]
+async fn extract_zip_reader_entry_to_file<R: Runtime>(
+    app_handle: AppHandle<R>,
+    id: u64,
+    path: &str,
+    dest: &str,
+) -> Result<(), String> {
+    let dest_path = get_allowed_path_with_string_error(&app_handle, dest)?;
+    do_with_reader(&app_handle, id, |reader| {
+        reader.extract_entry_to_file(path, dest_path)
+    })
+}
+
+#[tauri::command]
+async fn load_zip_writer<R: Runtime>(
+    app_handle: AppHandle<R>,
+    window: Window<R>,
+    source: &str,
+    options: Option<ZipWriterOptions>,
+) -> Result<u64, String> {
     let source_path = get_allowed_path_with_string_error(&app_handle, source)?;
-    let create_result = || -> Result<usize, ZipError> {
-        let file = File::create(source_path)?;
-        let buf_writer = BufWriter::new(file);
-        let writer = ZipWriter::new(buf_writer);
-        Ok(ZipWriterHelper::register_writer(&app_handle, writer))
-    }();
-    create_result.map_err(|error| error.to_string())
+    let helper = ZipWriterHelper::create(source_path, options.unwrap_or_default())
+        .map_err(|err| err.to_string())?;
+    ZipWriterHelper::register_writer(&window, helper)
 }
 
 #[tauri::command]
-fn close_zip_writer<R: Runtime>(app_handle: AppHandle<R>, id: usize) -> Result<(), String> {
+async fn close_zip_writer<R: Runtime>(app_handle: AppHandle<R>, id: u64) -> Result<(), String> {
     ZipWriterHelper::free_writer(&app_handle, id)
 }
 
 #[tauri::command]
-fn add_zip_writer_directory<R: Runtime>(
+async fn add_zip_writer_directory<R: Runtime>(
+    app_handle: AppHandle<R>,
+    id: u64,
+    path: &str,
+    options: Option<ZipEntryOptions>,
+) -> Result<(), String> {
+    do_with_writer(&app_handle, id, |writer| {
+        writer.add_directory(path, options.as_ref())
+    })
+}
+
+#[tauri::command]
+async fn delete_zip_writer_entry<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
 ) -> Result<(), String> {
-    do_with_writer(&app_handle, id, |writer| writer.add_directory(path))
+    do_with_writer(&app_handle, id, |writer| writer.delete_entry(path))
 }
 
 #[tauri::command]
-fn write_zip_writer_entry_from_binary<R: Runtime>(
+async fn write_zip_writer_entry_from_binary<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
     binary: Vec<u8>,
+    options: Option<ZipEntryOptions>,
 ) -> Result<(), String> {
     do_with_writer(&app_handle, id, |writer| {
-        writer.write_entry_from_binary(path, &binary)
+        writer.write_entry_from_binary(path, &binary, options.as_ref())
     })
 }
 
 #[tauri::command]
-fn write_zip_writer_entry_from_text<R: Runtime>(
+async fn write_zip_writer_entry_from_text<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
     text: &str,
+    options: Option<ZipEntryOptions>,
 ) -> Result<(), String> {
     do_with_writer(&app_handle, id, |writer| {
-        writer.write_entry_from_text(path, text)
+        writer.write_entry_from_text(path, text, options.as_ref())
     })
 }
 
 #[tauri::command]
-fn write_zip_writer_entry_from_file<R: Runtime>(
+async fn write_zip_writer_entry_from_file<R: Runtime>(
     app_handle: AppHandle<R>,
-    id: usize,
+    id: u64,
     path: &str,
     source: &str,
+    options: Option<ZipEntryOptions>,
 ) -> Result<(), String> {
     let source_path = get_allowed_path_with_string_error(&app_handle, source)?;
     do_with_writer(&app_handle, id, |writer| {
-        writer.write_entry_from_file(path, source_path)
+        writer.write_entry_from_file(path, source_path, options.as_ref())
     })
 }
 
-// careless, overwrites, may leave remains on error
+fn register_extraction_job<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    job_id: &str,
+) -> Result<Arc<AtomicBool>, ZipSupportError> {
+    let mut jobs = get_state_mutex_from_handle::<R, ZipExtractionJobs>(app_handle);
+    if jobs.jobs.contains_key(job_id) {
+        return Err(ZipSupportError::Key("zip.job.id.taken"));
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    jobs.jobs.insert(job_id.to_string(), cancel.clone());
+    Ok(cancel)
+}
+
+fn free_extraction_job<R: Runtime>(app_handle: &AppHandle<R>, job_id: &str) {
+    get_state_mutex_from_handle::<R, ZipExtractionJobs>(app_handle)
+        .jobs
+        .remove(job_id);
+}
+
+fn emit_extraction_progress<R: Runtime>(app_handle: &AppHandle<R>, progress: ExtractionProgress) {
+    if let Err(err) =
+        app_handle.emit_all::<ExtractionProgress>(ZIP_EXTRACT_PROGRESS_EVENT, progress)
+    {
+        error!("Failed to emit zip extraction progress: {}", err);
+    }
+}
+
+// overwrites, removes the created files again on error or cancel, but overwritten files stay changed,
+// unless the extraction is transactional
+// nothing is extracted if an entry would end up outside the destination
+// like the reader, it also supports tar.gz and 7z archives
+// if a job id is given, progress events are send and the extraction can be cancelled with it
 // async (other thread), since it does not care about other stuff
 #[tauri::command]
 async fn extract_zip_to_path<R: Runtime>(
     app_handle: AppHandle<R>,
     source: &str,
     dest: &str,
-) -> Result<(), String> {
-    let source_path = get_allowed_path_with_string_error(&app_handle, source)?;
-    let dist_path = get_allowed_path_with_string_error(&app_handle, dest)?;
+    job_id: Option<String>,
+    options: Option<ExtractionOptions>,
+) -> Result<(), ZipSupportError> {
+    let source_path = get_allowed_path(&app_handle, source)?;
+    let dist_path = get_allowed_path(&app_handle, dest)?;
+
+    let options = options.unwrap_or_default();
+    if let Some(manifest) = &options.manifest {
+        get_allowed_path(&app_handle, manifest)?;
+    }
+    let cancel = match &job_id {
+        Some(job_id) => register_extraction_job(&app_handle, job_id)?,
+        None => Arc::new(AtomicBool::new(false)),
+    };
+
+    let extract_result = || -> Result<(), ZipSupportError> {
+        let mut archive = ArchiveReader::open_file(source_path)?;
+        extraction::extract(&mut archive, dist_path, &options, &cancel, |progress| {
+            if let Some(job_id) = &job_id {
+                emit_extraction_progress(
+                    &app_handle,
+                    ExtractionProgress {
+                        job_id: job_id.clone(),
+                        entries_done: progress.entries_done,
+                        entries_total: progress.entries_total,
+                        bytes_written: progress.bytes_written,
+                        entry: progress.entry.to_string(),
+                    },
+                );
+            }
+        })
+    }();
+
+    if let Some(job_id) = &job_id {
+        free_extraction_job(&app_handle, job_id);
+    }
+    extract_result
+}
+
+// removes the files recorded in the manifest of an extraction and restores the files they replaced
+// async (other thread), since it does not care about other stuff
+#[tauri::command]
+async fn uninstall_zip_extraction<R: Runtime>(
+    app_handle: AppHandle<R>,
+    manifest: &str,
+    options: Option<UninstallOptions>,
+) -> Result<UninstallResult, ZipSupportError> {
+    let manifest_path = get_allowed_path(&app_handle, manifest)?;
+    let dest = manifest::get_manifest_dest(manifest_path)?;
+    if !app_handle.fs_scope().is_allowed(&dest) {
+        return Err(tauri::Error::PathNotAllowed(dest).into());
+    }
+    manifest::uninstall(manifest_path, &options.unwrap_or_default())
+}
+
+// reads every entry to validate the checksums, also reports unsafe, duplicate and case-colliding names
+// returns an empty list if no problem was found
+// async (other thread), since it does not care about other stuff
+#[tauri::command]
+async fn verify_zip<R: Runtime>(
+    app_handle: AppHandle<R>,
+    source: &str,
+) -> Result<Vec<ZipProblem>, ZipSupportError> {
+    let source_path = get_allowed_path(&app_handle, source)?;
+    let file = File::open(source_path)?;
+    let archive_size = file.metadata()?.len();
+    let mut archive = ZipArchive::new(BufReader::new(file))?;
+    verify::verify(&mut archive, archive_size)
+}
+
+// compares the files of the reader with an archive or folder, like an installed version of it
+// files only in the reader are reported as added, files only in the base as removed
+// async (other thread), since it does not care about other stuff
+#[tauri::command]
+async fn diff_zip_reader<R: Runtime>(
+    app_handle: AppHandle<R>,
+    id: u64,
+    base: &str,
+    options: Option<DiffOptions>,
+) -> Result<ArchiveDiff, ZipSupportError> {
+    let base_path = get_allowed_path(&app_handle, base)?;
+    let mut base_archive;
+    let mut base_side = if base_path.is_dir() {
+        DiffSide::Folder(base_path.to_path_buf())
+    } else {
+        base_archive = ArchiveReader::open_file(base_path)?;
+        limits::check_archive(&base_archive, &ZipLimits::default())?;
+        DiffSide::Archive(&mut base_archive)
+    };
+
+    let reader = ZipReaderHelper::get_reader(&app_handle, id)
+        .map_err(|_| ZipSupportError::Key("zip.id.missing"))?;
+    let mut reader = reader.lock().unwrap();
+    diff::diff(
+        &mut base_side,
+        &mut DiffSide::Archive(&mut reader.reader),
+        &options.unwrap_or_default(),
+    )
+}
 
-    let extract_result = || -> Result<(), ZipError> {
-        let file = fs::File::open(source_path)?;
-        let mut archive = ZipArchive::new(file)?;
-        archive.extract(dist_path)
+fn emit_pack_progress<R: Runtime>(app_handle: &AppHandle<R>, progress: PackProgress) {
+    if let Err(err) = app_handle.emit_all::<PackProgress>(ZIP_PACK_PROGRESS_EVENT, progress) {
+        error!("Failed to emit zip pack progress: {}", err);
+    }
+}
+
+// packs all files of the source folder, the archive is removed again on error or cancel
+// if a job id is given, progress events are send and the pack can be cancelled with it
+// returns the sha256 of the created archive
+// async (other thread), since it does not care about other stuff
+#[tauri::command]
+async fn pack_directory_to_zip<R: Runtime>(
+    app_handle: AppHandle<R>,
+    source: &str,
+    dest: &str,
+    job_id: Option<String>,
+    options: Option<PackOptions>,
+) -> Result<String, ZipSupportError> {
+    let source_path = get_allowed_path(&app_handle, source)?;
+    let dest_path = get_allowed_path(&app_handle, dest)?;
+
+    let options = options.unwrap_or_default();
+    let cancel = match &job_id {
+        Some(job_id) => register_extraction_job(&app_handle, job_id)?,
+        None => Arc::new(AtomicBool::new(false)),
+    };
+
+    let pack_result = || -> Result<String, ZipSupportError> {
+        let mut writer = ZipWriterHelper::create(dest_path, options.writer.clone())?;
+        let fs_scope = app_handle.fs_scope();
+        let is_allowed = |path: &Path| fs_scope.is_allowed(path);
+        let packed = pack::pack(
+            &mut writer,
+            source_path,
+            &options,
+            is_allowed,
+            &cancel,
+            |progress| {
+                if let Some(job_id) = &job_id {
+                    emit_pack_progress(
+                        &app_handle,
+                        PackProgress {
+                            job_id: job_id.clone(),
+                            files_done: progress.files_done,
+                            files_total: progress.files_total,
+                            bytes_written: progress.bytes_written,
+                            entry: progress.entry.to_string(),
+                        },
+                    );
+                }
+            },
+        );
+        if let Err(err) = packed {
+            writer.discard();
+            return Err(err);
+        }
+        if let Err(err) = writer.finish() {
+            writer.discard();
+            return Err(err.into());
+        }
+        Ok(get_sha256_of_path(dest_path)?)
     }();
-    extract_result.map_err(|error| error.to_string())
+
+    if let Some(job_id) = &job_id {
+        free_extraction_job(&app_handle, job_id);
+    }
+    pack_result
+}
+
+// only marks the job as cancelled, the running extraction or pack will stop and return the cancel error
+#[tauri::command]
+fn cancel_zip_extraction<R: Runtime>(app_handle: AppHandle<R>, job_id: &str) -> Result<(), String> {
+    get_state_mutex_from_handle::<R, ZipExtractionJobs>(&app_handle)
+        .jobs
+        .get(job_id)
+        .ok_or(String::from("zip.job.id.missing"))?
+        .store(true, Ordering::Relaxed);
+    Ok(())
 }
 
 /// INIT ///
 
+const PLUGIN_NAME: &str = "tauri-plugin-ucp-zip-support";
+
+// sync, so it is handled before any command of the new page, see the init script
+#[tauri::command]
+fn page_started<R: Runtime>(window: Window<R>) {
+    free_window_handles(&window.app_handle(), window.label())
+}
+
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("tauri-plugin-ucp-zip-support")
+    Builder::new(PLUGIN_NAME)
+        .js_init_script(get_page_start_script(PLUGIN_NAME, "page_started"))
         .invoke_handler(tauri::generate_handler![
+            page_started,
             extract_zip_to_path,
+            cancel_zip_extraction,
+            uninstall_zip_extraction,
+            pack_directory_to_zip,
+            verify_zip,
+            diff_zip_reader,
             load_zip_reader,
+            load_zip_reader_from_entry,
+            load_zip_reader_from_binary,
             close_zip_reader,
             is_zip_reader_empty,
             get_zip_reader_number_of_entries,
             exist_zip_reader_entry,
             get_zip_reader_entry_names,
+            get_zip_reader_entry_metadata,
             get_zip_reader_entry_as_binary,
             get_zip_reader_entry_as_text,
+            open_zip_reader_entry_stream,
+            read_zip_reader_entry_stream,
+            close_zip_reader_entry_stream,
+            extract_zip_reader_entry_to_file,
             load_zip_writer,
             close_zip_writer,
             add_zip_writer_directory,
+            delete_zip_writer_entry,
             write_zip_writer_entry_from_binary,
             write_zip_writer_entry_from_text,
             write_zip_writer_entry_from_file
         ])
         .setup(|app_handle| {
             app_handle.manage::<Mutex<ZipCollectionsState>>(Mutex::new(ZipCollectionsState::new()));
+            app_handle.manage::<Mutex<ZipExtractionJobs>>(Mutex::new(ZipExtractionJobs::new()));
+
+            // handles the frontend forgot to close are freed after a while
+            let sweep_handle = app_handle.clone();
+            thread::spawn(move || loop {
+                thread::sleep(ZIP_HANDLE_SWEEP_INTERVAL);
+                free_idle_handles(&sweep_handle);
+            });
             Ok(())
         })
+        .on_event(|app_handle: &AppHandle<R>, event| {
+            if let RunEvent::WindowEvent {
+                label,
+                event: WindowEvent::Destroyed,
+                ..
+            } = event
+            {
+                free_window_handles(app_handle, label)
+            }
+        })
         .build()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn create_archive(path: &Path, entries: &[(&str, &str)]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn read_archive(path: &Path) -> Vec<(String, String)> {
+        let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        (0..archive.len())
+            .map(|index| {
+                let mut file = archive.by_index(index).unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+
+    fn update_options() -> ZipWriterOptions {
+        ZipWriterOptions {
+            update: true,
+            ..Default::default()
+        }
+    }
+
+    fn folder_content(folder: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn update_keeps_unchanged_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        create_archive(&target, &[("a.txt", "a"), ("b.txt", "b")]);
+
+        let mut writer = ZipWriterHelper::create(&target, update_options()).unwrap();
+        writer.write_entry_from_text("c.txt", "c", None).unwrap();
+        // the original stays until the writer is finished
+        assert_eq!(read_archive(&target).len(), 2);
+        writer.finish().unwrap();
+
+        let entries = read_archive(&target);
+        assert_eq!(entries.len(), 3);
+        assert!(entries.contains(&(String::from("a.txt"), String::from("a"))));
+        assert!(entries.contains(&(String::from("c.txt"), String::from("c"))));
+        assert_eq!(folder_content(temp.path()), vec!["archive.zip"]);
+    }
+
+    #[test]
+    fn update_replaces_written_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        create_archive(&target, &[("a.txt", "a"), ("b.txt", "b")]);
+
+        let mut writer = ZipWriterHelper::create(&target, update_options()).unwrap();
+        writer.write_entry_from_text("b.txt", "new", None).unwrap();
+        writer.finish().unwrap();
+
+        let mut entries = read_archive(&target);
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                (String::from("a.txt"), String::from("a")),
+                (String::from("b.txt"), String::from("new"))
+            ]
+        );
+    }
+
+    #[test]
+    fn update_deletes_entries_and_folders() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        create_archive(
+            &target,
+            &[("a.txt", "a"), ("folder/b.txt", "b"), ("folder/c.txt", "c")],
+        );
+
+        let mut writer = ZipWriterHelper::create(&target, update_options()).unwrap();
+        assert_eq!(
+            writer.delete_entry("missing.txt").unwrap_err(),
+            "zip.writer.entry.missing"
+        );
+        writer.delete_entry("folder/").unwrap();
+        // deleted entries can be written again
+        writer.delete_entry("a.txt").unwrap();
+        writer.write_entry_from_text("a.txt", "new", None).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            read_archive(&target),
+            vec![(String::from("a.txt"), String::from("new"))]
+        );
+    }
+
+    #[test]
+    fn delete_requires_update_mode() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        let mut writer = ZipWriterHelper::create(&target, ZipWriterOptions::default()).unwrap();
+        assert_eq!(
+            writer.delete_entry("a.txt").unwrap_err(),
+            "zip.writer.update.required"
+        );
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn failed_update_removes_staging_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        create_archive(&target, &[("a.txt", "a"), ("b.txt", "b")]);
+
+        let mut writer = ZipWriterHelper::create(&target, update_options()).unwrap();
+        writer.write_entry_from_text("c.txt", "c", None).unwrap();
+        // the kept entries can no longer be copied
+        File::options()
+            .write(true)
+            .open(&target)
+            .unwrap()
+            .set_len(10)
+            .unwrap();
+        assert!(writer.finish().is_err());
+        writer.discard();
+
+        assert_eq!(folder_content(temp.path()), vec!["archive.zip"]);
+    }
+
+    #[test]
+    fn failed_reproducible_replace_keeps_target() {
+        let temp = tempfile::tempdir().unwrap();
+        // a folder can not be replaced by the sorted archive
+        let target = temp.path().join("archive.zip");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("keep.txt"), "keep").unwrap();
+
+        let options = ZipWriterOptions {
+            reproducible: true,
+            ..Default::default()
+        };
+        let mut writer = ZipWriterHelper::create(&target, options).unwrap();
+        writer.write_entry_from_text("b.txt", "b", None).unwrap();
+        writer.write_entry_from_text("a.txt", "a", None).unwrap();
+        assert!(writer.finish().is_err());
+        writer.discard();
+
+        assert_eq!(folder_content(temp.path()), vec!["archive.zip"]);
+        assert_eq!(folder_content(&target), vec!["keep.txt"]);
+    }
+
+    #[test]
+    fn reproducible_sorts_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        let target = temp.path().join("archive.zip");
+        let options = ZipWriterOptions {
+            reproducible: true,
+            ..Default::default()
+        };
+        let mut writer = ZipWriterHelper::create(&target, options).unwrap();
+        writer.write_entry_from_text("b.txt", "b", None).unwrap();
+        writer.write_entry_from_text("a.txt", "a", None).unwrap();
+        writer.finish().unwrap();
+
+        let names: Vec<String> = read_archive(&target)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["a.txt", "b.txt"]);
+        assert_eq!(folder_content(temp.path()), vec!["archive.zip"]);
+    }
+}
@@ -0,0 +1,479 @@
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use sevenz_rust::BlockDecoder;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use zip::{result::ZipError, ZipArchive};
+
+use super::{error::ZipSupportError, limits::LimitedReader};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const SEVEN_Z_MAGIC: &[u8] = &[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c];
+
+const UNIX_FILE_TYPE_MASK: u32 = 0o170000;
+const UNIX_SYMLINK_TYPE: u32 = 0o120000;
+
+// 7z stores the unix mode in the upper bits of the attributes if this flag is set
+const SEVEN_Z_UNIX_EXTENSION_FLAG: u32 = 0x8000;
+
+enum ArchiveFormat {
+    Zip,
+    TarGz,
+    SevenZ,
+}
+
+/// An entry of an archive, independent of the format.
+/// Folders always end with a slash, like in zip archives.
+#[derive(Serialize, Clone)]
+pub struct ArchiveEntry {
+    pub name: String,
+    /// Only known if the entries are compressed separately, like in zip archives.
+    pub compressed_size: Option<u64>,
+    pub size: u64,
+    pub crc32: Option<u32>,
+    pub last_modified: Option<String>, // local time without zone, like stored in zip archives
+    pub compression_method: Option<String>,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub unix_mode: Option<u32>,
+}
+
+type EntryCallback<'a> = dyn FnMut(usize, &mut dyn Read) -> Result<(), ZipSupportError> + 'a;
+
+/// Access to the content of the entries of a single format.
+trait ArchiveContent: Send {
+    /// Calls the callback with the content of the given entries, indices are in ascending order.
+    /// The order of the calls follows the archive, which is not necessarily the order of the indices.
+    fn read_entries(
+        &mut self,
+        indices: &[usize],
+        each: &mut EntryCallback,
+    ) -> Result<(), ZipSupportError>;
+}
+
+/// Where an archive is read from, so it can be opened again, like for entry streams.
+#[derive(Clone)]
+pub enum ArchiveSource {
+    File(PathBuf),
+    /// Shared, so reopening does not copy the archive.
+    Memory(Arc<[u8]>),
+}
+
+impl ArchiveSource {
+    pub fn open(&self) -> Result<ArchiveReader, ZipSupportError> {
+        match self {
+            ArchiveSource::File(path) => ArchiveReader::open_file(path),
+            ArchiveSource::Memory(bytes) => ArchiveReader::open(Cursor::new(bytes.clone())),
+        }
+    }
+}
+
+/// An opened zip, tar.gz or 7z archive.
+/// The format is detected by the first bytes of the file.
+/// Zip is used for everything else, since zip archives are read from the end.
+pub struct ArchiveReader {
+    size: u64,
+    entries: Vec<ArchiveEntry>,
+    content: Box<dyn ArchiveContent>,
+}
+
+fn detect_format<R: Read + Seek>(source: &mut R) -> io::Result<ArchiveFormat> {
+    let mut magic = Vec::with_capacity(SEVEN_Z_MAGIC.len());
+    source
+        .by_ref()
+        .take(SEVEN_Z_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    source.seek(SeekFrom::Start(0))?;
+
+    let format = if magic.starts_with(SEVEN_Z_MAGIC) {
+        ArchiveFormat::SevenZ
+    } else if magic.starts_with(GZIP_MAGIC) {
+        ArchiveFormat::TarGz
+    } else {
+        ArchiveFormat::Zip
+    };
+    Ok(format)
+}
+
+fn is_symlink_mode(unix_mode: Option<u32>) -> bool {
+    unix_mode.is_some_and(|mode| mode & UNIX_FILE_TYPE_MASK == UNIX_SYMLINK_TYPE)
+}
+
+fn to_folder_name(mut name: String) -> String {
+    if !name.ends_with('/') {
+        name.push('/');
+    }
+    name
+}
+
+impl ArchiveReader {
+    pub fn open<R: Read + Seek + Send + 'static>(
+        mut source: R,
+    ) -> Result<ArchiveReader, ZipSupportError> {
+        let format = detect_format(&mut source)?;
+        let size = source.seek(SeekFrom::End(0))?;
+        source.seek(SeekFrom::Start(0))?;
+
+        let (entries, content): (Vec<ArchiveEntry>, Box<dyn ArchiveContent>) = match format {
+            ArchiveFormat::Zip => {
+                let mut archive = ZipArchive::new(source)?;
+                (list_zip_entries(&mut archive)?, Box::new(archive))
+            }
+            ArchiveFormat::TarGz => {
+                let mut content = TarGzContent { source };
+                (content.list_entries()?, Box::new(content))
+            }
+            ArchiveFormat::SevenZ => {
+                let content = SevenZContent::open(source, size)?;
+                (content.list_entries(), Box::new(content))
+            }
+        };
+        Ok(ArchiveReader {
+            size,
+            entries,
+            content,
+        })
+    }
+
+    pub fn open_file(path: &Path) -> Result<ArchiveReader, ZipSupportError> {
+        ArchiveReader::open(BufReader::new(File::open(path)?))
+    }
+
+    /// Size of the archive file.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    // the last entry wins, like for zip archives
+    pub fn find_entry(&self, name: &str) -> Option<usize> {
+        self.entries.iter().rposition(|entry| entry.name == name)
+    }
+
+    /// Reads the given entries in a single pass, which is the fastest way for formats without random access.
+    /// No entry can decompress to more bytes than it claims, so the checked sizes can be trusted.
+    pub fn read_entries<F>(&mut self, indices: &[usize], mut each: F) -> Result<(), ZipSupportError>
+    where
+        F: FnMut(usize, &ArchiveEntry, &mut dyn Read) -> Result<(), ZipSupportError>,
+    {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices
+            .last()
+            .is_some_and(|index| *index >= self.entries.len())
+        {
+            return Err(ZipSupportError::Zip(ZipError::FileNotFound));
+        }
+
+        let entries = &self.entries;
+        self.content.read_entries(&indices, &mut |index, reader| {
+            let entry = &entries[index];
+            each(
+                index,
+                entry,
+                &mut LimitedReader::new(reader, entry.size, &entry.name),
+            )
+        })
+    }
+
+    pub fn read_entry<F>(&mut self, name: &str, read: F) -> Result<(), ZipSupportError>
+    where
+        F: FnOnce(&mut dyn Read) -> Result<(), ZipSupportError>,
+    {
+        let index = self
+            .find_entry(name)
+            .ok_or(ZipSupportError::Zip(ZipError::FileNotFound))?;
+        let mut read = Some(read);
+        self.read_entries(&[index], |_, _, reader| match read.take() {
+            Some(read) => read(reader),
+            None => Ok(()),
+        })
+    }
+}
+
+/// ZIP ///
+
+/// Lists the entries without decompressing them.
+pub fn list_zip_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Vec<ArchiveEntry>, ZipError> {
+    let mut entries = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        let date_time = file.last_modified();
+        entries.push(ArchiveEntry {
+            name: file.name().to_string(),
+            compressed_size: Some(file.compressed_size()),
+            size: file.size(),
+            crc32: Some(file.crc32()),
+            last_modified: Some(format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                date_time.year(),
+                date_time.month(),
+                date_time.day(),
+                date_time.hour(),
+                date_time.minute(),
+                date_time.second()
+            )),
+            compression_method: Some(file.compression().to_string()),
+            is_dir: file.is_dir(),
+            is_symlink: is_symlink_mode(file.unix_mode()),
+            unix_mode: file.unix_mode(),
+        });
+    }
+    Ok(entries)
+}
+
+impl<R: Read + Seek + Send> ArchiveContent for ZipArchive<R> {
+    fn read_entries(
+        &mut self,
+        indices: &[usize],
+        each: &mut EntryCallback,
+    ) -> Result<(), ZipSupportError> {
+        for index in indices {
+            each(*index, &mut self.by_index(*index)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// TAR.GZ ///
+
+// the whole archive is compressed, so every access decompresses it from the start
+struct TarGzContent<R: Read + Seek> {
+    source: R,
+}
+
+enum TarEntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+// other types, like devices or global headers, are not part of the listed entries
+fn get_tar_entry_kind<R: Read>(
+    entry: &tar::Entry<R>,
+    name: &str,
+) -> Result<Option<TarEntryKind>, ZipSupportError> {
+    let entry_type = entry.header().entry_type();
+    if entry_type.is_file() || entry_type.is_contiguous() {
+        Ok(Some(TarEntryKind::File))
+    } else if entry_type.is_dir() {
+        Ok(Some(TarEntryKind::Dir))
+    } else if entry_type.is_symlink() {
+        Ok(Some(TarEntryKind::Symlink))
+    } else if entry_type.is_hard_link() {
+        // would point to another entry of the archive, which can not be represented as a file
+        Err(ZipSupportError::UnsafeEntry {
+            code: "zip.entry.link.unsupported",
+            entry: name.to_string(),
+        })
+    } else {
+        Ok(None)
+    }
+}
+
+// symlinks store their target as content, like in zip archives
+fn get_tar_link_target<R: Read>(entry: &tar::Entry<R>) -> Vec<u8> {
+    entry
+        .link_name_bytes()
+        .map(|target| target.into_owned())
+        .unwrap_or_default()
+}
+
+impl<R: Read + Seek> TarGzContent<R> {
+    fn for_each_tar_entry<F>(&mut self, mut each: F) -> Result<(), ZipSupportError>
+    where
+        F: FnMut(
+            usize,
+            &mut tar::Entry<GzDecoder<&mut R>>,
+            TarEntryKind,
+            String,
+        ) -> Result<bool, ZipSupportError>,
+    {
+        self.source.seek(SeekFrom::Start(0))?;
+        let mut archive = tar::Archive::new(GzDecoder::new(&mut self.source));
+        let mut index = 0;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+            let kind = match get_tar_entry_kind(&entry, &name)? {
+                Some(kind) => kind,
+                None => continue,
+            };
+            if !each(index, &mut entry, kind, name)? {
+                break;
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    fn list_entries(&mut self) -> Result<Vec<ArchiveEntry>, ZipSupportError> {
+        let mut entries = Vec::new();
+        self.for_each_tar_entry(|_, entry, kind, name| {
+            let (name, size) = match kind {
+                TarEntryKind::File => (name, entry.size()),
+                TarEntryKind::Dir => (to_folder_name(name), 0),
+                TarEntryKind::Symlink => (name, get_tar_link_target(entry).len() as u64),
+            };
+            entries.push(ArchiveEntry {
+                name,
+                compressed_size: None,
+                size,
+                crc32: None,
+                last_modified: None,
+                compression_method: None,
+                is_dir: matches!(kind, TarEntryKind::Dir),
+                is_symlink: matches!(kind, TarEntryKind::Symlink),
+                unix_mode: entry.header().mode().ok(),
+            });
+            Ok(true)
+        })?;
+        Ok(entries)
+    }
+}
+
+impl<R: Read + Seek + Send> ArchiveContent for TarGzContent<R> {
+    fn read_entries(
+        &mut self,
+        indices: &[usize],
+        each: &mut EntryCallback,
+    ) -> Result<(), ZipSupportError> {
+        let mut remaining_indices = indices.iter().peekable();
+        self.for_each_tar_entry(|index, entry, kind, _| {
+            if remaining_indices.peek() != Some(&&index) {
+                return Ok(true);
+            }
+            remaining_indices.next();
+            match kind {
+                TarEntryKind::Symlink => each(index, &mut get_tar_link_target(entry).as_slice())?,
+                _ => each(index, entry)?,
+            }
+            Ok(remaining_indices.peek().is_some())
+        })
+    }
+}
+
+/// 7Z ///
+
+struct SevenZContent<R: Read + Seek> {
+    source: R,
+    archive: sevenz_rust::Archive,
+    file_indices: Vec<usize>, // index in the 7z archive of every listed entry
+}
+
+impl<R: Read + Seek> SevenZContent<R> {
+    fn open(mut source: R, size: u64) -> Result<SevenZContent<R>, ZipSupportError> {
+        let archive = sevenz_rust::Archive::read(&mut source, size, &[])?;
+        // anti items mark deleted files of an update and have no content
+        let file_indices = (0..archive.files.len())
+            .filter(|file_index| !archive.files[*file_index].is_anti_item())
+            .collect();
+        Ok(SevenZContent {
+            source,
+            archive,
+            file_indices,
+        })
+    }
+
+    fn list_entries(&self) -> Vec<ArchiveEntry> {
+        self.file_indices
+            .iter()
+            .map(|file_index| {
+                let file = &self.archive.files[*file_index];
+                let unix_mode = if file.has_windows_attributes
+                    && file.windows_attributes & SEVEN_Z_UNIX_EXTENSION_FLAG != 0
+                {
+                    Some(file.windows_attributes >> 16)
+                } else {
+                    None
+                };
+                let name = if file.is_directory() {
+                    to_folder_name(file.name().to_string())
+                } else {
+                    file.name().to_string()
+                };
+                ArchiveEntry {
+                    name,
+                    compressed_size: None,
+                    size: file.size(),
+                    crc32: file.has_crc.then_some(file.crc as u32),
+                    last_modified: None,
+                    compression_method: None,
+                    is_dir: file.is_directory(),
+                    is_symlink: is_symlink_mode(unix_mode),
+                    unix_mode,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<R: Read + Seek + Send> ArchiveContent for SevenZContent<R> {
+    fn read_entries(
+        &mut self,
+        indices: &[usize],
+        each: &mut EntryCallback,
+    ) -> Result<(), ZipSupportError> {
+        let wanted: HashMap<usize, usize> = indices
+            .iter()
+            .map(|index| (self.file_indices[*index], *index))
+            .collect();
+        let stream_map = &self.archive.stream_map;
+
+        // entries of a block depend on the ones before, so a block is always decoded from its start
+        for (folder_index, folder) in self.archive.folders.iter().enumerate() {
+            let first_file_index = stream_map.folder_first_file_index[folder_index];
+            let file_range = first_file_index..first_file_index + folder.num_unpack_sub_streams;
+            let last_wanted = match file_range
+                .rev()
+                .find(|file_index| wanted.contains_key(file_index))
+            {
+                Some(last_wanted) => last_wanted,
+                None => continue,
+            };
+
+            let mut file_index = first_file_index;
+            let mut each_error = None;
+            BlockDecoder::new(folder_index, &self.archive, &[], &mut self.source)
+                .for_each_entries(&mut |_, reader| {
+                    match wanted.get(&file_index) {
+                        Some(index) => {
+                            if let Err(error) = each(*index, reader) {
+                                each_error = Some(error);
+                                return Ok(false);
+                            }
+                        }
+                        // skipped entries still need to be decoded
+                        None => {
+                            io::copy(reader, &mut io::sink())?;
+                        }
+                    }
+                    file_index += 1;
+                    Ok(file_index <= last_wanted)
+                })?;
+            if let Some(error) = each_error {
+                return Err(error);
+            }
+        }
+
+        // entries without content, like folders, are not part of a block
+        for index in indices {
+            let file_index = self.file_indices[*index];
+            if stream_map.file_folder_index[file_index].is_none() {
+                each(*index, &mut io::empty())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::{self, File},
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use super::{archive::ArchiveReader, error::ZipSupportError};
+use crate::{constants::ZIP_DIFF_MAX_TEXT_BYTES, hash_utils::collect_folder_files};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiffOptions {
+    /// Adds a unified diff to modified files with one of these extensions.
+    pub text_diff: bool,
+    pub text_extensions: Vec<String>,
+    /// Lines of unchanged context around every change of a text diff.
+    pub context_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            text_diff: false,
+            text_extensions: vec![
+                String::from(".lua"),
+                String::from(".yml"),
+                String::from(".yaml"),
+                String::from(".md"),
+            ],
+            context_lines: 3,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ModifiedFile {
+    pub path: String,
+    pub base_size: u64,
+    pub size: u64,
+    /// Only set for text files, if requested and both versions are small enough and valid utf-8.
+    pub text_diff: Option<String>,
+}
+
+/// Differences of the files, folders are not compared.
+#[derive(Serialize, Default)]
+pub struct ArchiveDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<ModifiedFile>,
+}
+
+/// One side of the comparison.
+pub enum DiffSide<'a> {
+    Archive(&'a mut ArchiveReader),
+    Folder(PathBuf),
+}
+
+struct SideFile {
+    size: u64,
+    crc32: Option<u32>,
+    index: usize, // only for archives
+}
+
+struct FileDigest {
+    sha256: String,
+    text: Option<String>,
+}
+
+impl DiffSide<'_> {
+    // later entries with the same name win, like when extracting
+    fn list_files(&self) -> Result<BTreeMap<String, SideFile>, ZipSupportError> {
+        let mut files = BTreeMap::new();
+        match self {
+            DiffSide::Archive(archive) => {
+                for (index, entry) in archive.entries().iter().enumerate() {
+                    if entry.is_dir {
+                        continue;
+                    }
+                    files.insert(
+                        entry.name.clone(),
+                        SideFile {
+                            size: entry.size,
+                            crc32: entry.crc32,
+                            index,
+                        },
+                    );
+                }
+            }
+            DiffSide::Folder(folder) => {
+                let mut relative_paths = Vec::new();
+                collect_folder_files(folder, folder, &mut relative_paths)?;
+                for relative_path in relative_paths {
+                    let size = fs::metadata(folder.join(&relative_path))?.len();
+                    files.insert(
+                        relative_path,
+                        SideFile {
+                            size,
+                            crc32: None,
+                            index: 0,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn digest_files(
+        &mut self,
+        files: &BTreeMap<String, SideFile>,
+        names: &BTreeSet<&str>,
+        text_names: &BTreeSet<&str>,
+    ) -> Result<HashMap<String, FileDigest>, ZipSupportError> {
+        let mut digests = HashMap::new();
+        match self {
+            DiffSide::Archive(archive) => {
+                let names_by_index: HashMap<usize, &str> = names
+                    .iter()
+                    .map(|name| (files[*name].index, *name))
+                    .collect();
+                let indices: Vec<usize> = names_by_index.keys().copied().collect();
+                archive.read_entries(&indices, |index, _, reader| {
+                    let name = names_by_index[&index];
+                    let digest = digest(reader, text_names.contains(name))?;
+                    digests.insert(name.to_string(), digest);
+                    Ok(())
+                })?;
+            }
+            DiffSide::Folder(folder) => {
+                for name in names {
+                    let mut file = File::open(folder.join(name))?;
+                    digests.insert(
+                        name.to_string(),
+                        digest(&mut file, text_names.contains(name))?,
+                    );
+                }
+            }
+        }
+        Ok(digests)
+    }
+}
+
+// the content is only kept for text files that are small enough
+fn digest(reader: &mut dyn Read, keep_text: bool) -> Result<FileDigest, io::Error> {
+    let mut hasher = Sha256::new();
+    let mut text = None;
+    if keep_text {
+        let mut content = Vec::new();
+        (&mut *reader)
+            .take(ZIP_DIFF_MAX_TEXT_BYTES + 1)
+            .read_to_end(&mut content)?;
+        hasher.update(&content);
+        if content.len() as u64 <= ZIP_DIFF_MAX_TEXT_BYTES {
+            text = String::from_utf8(content).ok();
+        }
+    }
+    io::copy(reader, &mut hasher)?;
+    Ok(FileDigest {
+        sha256: format!("{:x}", hasher.finalize()),
+        text,
+    })
+}
+
+fn create_text_diff(
+    path: &str,
+    base: Option<&FileDigest>,
+    other: Option<&FileDigest>,
+    context_lines: usize,
+) -> Option<String> {
+    let base_text = base?.text.as_deref()?;
+    let text = other?.text.as_deref()?;
+    Some(
+        TextDiff::from_lines(base_text, text)
+            .unified_diff()
+            .context_radius(context_lines)
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string(),
+    )
+}
+
+/// Compares the files of both sides by path, size and crc32, if both sides provide one.
+/// Otherwise, the contents are compared by sha256.
+/// Added files are only in the other side, removed files only in the base.
+pub fn diff(
+    base: &mut DiffSide,
+    other: &mut DiffSide,
+    options: &DiffOptions,
+) -> Result<ArchiveDiff, ZipSupportError> {
+    let base_files = base.list_files()?;
+    let other_files = other.list_files()?;
+    let is_text = |name: &str| {
+        options.text_diff
+            && options
+                .text_extensions
+                .iter()
+                .any(|extension| name.to_lowercase().ends_with(&extension.to_lowercase()))
+    };
+
+    let mut result = ArchiveDiff::default();
+    let mut changed = BTreeSet::new(); // modified for sure
+    let mut to_hash = BTreeSet::new(); // only the content can tell
+    for (name, base_file) in &base_files {
+        match other_files.get(name) {
+            None => result.removed.push(name.clone()),
+            Some(other_file) if other_file.size != base_file.size => {
+                changed.insert(name.as_str());
+            }
+            Some(other_file) => match (base_file.crc32, other_file.crc32) {
+                (Some(base_crc), Some(other_crc)) if base_crc != other_crc => {
+                    changed.insert(name.as_str());
+                }
+                (Some(_), Some(_)) => {}
+                _ => {
+                    to_hash.insert(name.as_str());
+                }
+            },
+        }
+    }
+    for name in other_files.keys() {
+        if !base_files.contains_key(name) {
+            result.added.push(name.clone());
+        }
+    }
+
+    let text_names: BTreeSet<&str> = changed
+        .iter()
+        .chain(to_hash.iter())
+        .copied()
+        .filter(|name| is_text(name))
+        .collect();
+    let to_read: BTreeSet<&str> = to_hash.union(&text_names).copied().collect();
+    let base_digests = base.digest_files(&base_files, &to_read, &text_names)?;
+    let other_digests = other.digest_files(&other_files, &to_read, &text_names)?;
+
+    for name in to_hash {
+        if base_digests[name].sha256 != other_digests[name].sha256 {
+            changed.insert(name);
+        }
+    }
+    for name in changed {
+        let text_diff = if text_names.contains(name) {
+            create_text_diff(
+                name,
+                base_digests.get(name),
+                other_digests.get(name),
+                options.context_lines,
+            )
+        } else {
+            None
+        };
+        result.modified.push(ModifiedFile {
+            path: name.to_string(),
+            base_size: base_files[name].size,
+            size: other_files[name].size,
+            text_diff,
+        });
+    }
+    Ok(result)
+}
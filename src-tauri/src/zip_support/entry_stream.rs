@@ -0,0 +1,64 @@
+use std::{
+    io::Read,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread,
+};
+use zip::result::ZipError;
+
+use super::{archive::ArchiveSource, error::ZipSupportError};
+
+const STREAM_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Reads a single entry in chunks.
+/// The entry is decompressed in its own thread that is at most one chunk ahead,
+/// so large entries are never fully held in memory.
+/// Dropping the stream stops the thread.
+pub struct ZipEntryStream {
+    receiver: Mutex<Receiver<Result<Vec<u8>, String>>>,
+}
+
+impl ZipEntryStream {
+    // uses its own archive instance, since entries borrow the archive they are read from
+    pub fn open(source: &ArchiveSource, entry: &str) -> Result<ZipEntryStream, ZipSupportError> {
+        let mut archive = source.open()?;
+        // fail early if missing
+        if archive.find_entry(entry).is_none() {
+            return Err(ZipSupportError::Zip(ZipError::FileNotFound));
+        }
+
+        let entry = entry.to_string();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            let read_result = archive.read_entry(&entry, |reader| loop {
+                let mut chunk = Vec::new();
+                if (&mut *reader)
+                    .take(STREAM_CHUNK_SIZE)
+                    .read_to_end(&mut chunk)?
+                    == 0
+                {
+                    return Ok(()); // dropping the sender ends the stream
+                }
+                if sender.send(Ok(chunk)).is_err() {
+                    return Err(ZipSupportError::Cancelled); // stream was closed
+                }
+            });
+            if let Err(err) = read_result {
+                let _ = sender.send(Err(err.into_string()));
+            }
+        });
+        Ok(ZipEntryStream {
+            receiver: Mutex::new(receiver),
+        })
+    }
+
+    /// Returns the next chunk, or None if the entry was fully read.
+    pub fn read_chunk(&self) -> Result<Option<Vec<u8>>, String> {
+        match self.receiver.lock().unwrap().recv() {
+            Ok(chunk_result) => chunk_result.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
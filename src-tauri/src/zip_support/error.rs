@@ -0,0 +1,110 @@
+use serde::ser::SerializeStruct;
+use std::io;
+use zip::result::ZipError;
+
+use super::limits::LimitExceeded;
+
+/// Error of the zip support, serialized as object with a code the frontend can react to,
+/// a readable message and the entry that caused the error, if there is one.
+#[derive(Debug, thiserror::Error)]
+pub enum ZipSupportError {
+    #[error("The extraction or pack was cancelled.")]
+    Cancelled,
+
+    #[error("The entry '{entry}' has an unsafe path and was rejected ({code}).")]
+    UnsafeEntry { code: &'static str, entry: String },
+
+    #[error("{0}")]
+    Key(&'static str),
+
+    #[error(transparent)]
+    Limit(LimitExceeded),
+
+    #[error(transparent)]
+    Zip(ZipError),
+
+    #[error(transparent)]
+    SevenZ(sevenz_rust::Error),
+
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+}
+
+// limit violations found while reading are send through io errors
+impl From<io::Error> for ZipSupportError {
+    fn from(error: io::Error) -> Self {
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<LimitExceeded>())
+        {
+            // checked above, so neither can fail
+            let limit = error
+                .into_inner()
+                .unwrap()
+                .downcast::<LimitExceeded>()
+                .unwrap();
+            return ZipSupportError::Limit(*limit);
+        }
+        ZipSupportError::Zip(ZipError::Io(error))
+    }
+}
+
+impl From<ZipError> for ZipSupportError {
+    fn from(error: ZipError) -> Self {
+        match error {
+            ZipError::Io(error) => error.into(),
+            error => ZipSupportError::Zip(error),
+        }
+    }
+}
+
+impl From<sevenz_rust::Error> for ZipSupportError {
+    fn from(error: sevenz_rust::Error) -> Self {
+        match error {
+            sevenz_rust::Error::Io(error, _) => error.into(),
+            error => ZipSupportError::SevenZ(error),
+        }
+    }
+}
+
+impl ZipSupportError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ZipSupportError::Cancelled => "zip.extract.cancelled",
+            ZipSupportError::UnsafeEntry { code, .. } => code,
+            ZipSupportError::Key(key) => key,
+            ZipSupportError::Limit(limit) => limit.code,
+            ZipSupportError::Zip(_) | ZipSupportError::SevenZ(_) => "zip.error",
+            ZipSupportError::Tauri(_) => "zip.path.not.allowed",
+        }
+    }
+
+    pub fn entry(&self) -> Option<&str> {
+        match self {
+            ZipSupportError::UnsafeEntry { entry, .. } => Some(entry),
+            ZipSupportError::Limit(limit) => limit.entry.as_deref(),
+            _ => None,
+        }
+    }
+
+    // for commands that only return strings, limit violations are returned as their code
+    pub fn into_string(self) -> String {
+        match self {
+            ZipSupportError::Limit(limit) => limit.code.to_string(),
+            error => error.to_string(),
+        }
+    }
+}
+
+impl serde::Serialize for ZipSupportError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        let mut state = serializer.serialize_struct("ZipSupportError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("entry", &self.entry())?;
+        state.end()
+    }
+}
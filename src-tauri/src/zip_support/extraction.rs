@@ -0,0 +1,624 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::scope::GlobPattern;
+
+use super::{
+    archive::ArchiveReader,
+    error::ZipSupportError,
+    limits::{self, ZipLimits},
+    manifest::ManifestRecorder,
+    safety,
+};
+use crate::constants::PATH_MATCH_OPTIONS;
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+const STAGING_FOLDER_PREFIX: &str = ".ucp-extract-staging-";
+const BACKUP_FOLDER_PREFIX: &str = ".ucp-extract-backup-";
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExtractionOptions {
+    /// Extract to a staging folder first and only swap the files in if everything succeeded.
+    /// Otherwise, files are written directly, but overwritten files are still restored on failure.
+    pub transactional: bool,
+
+    /// Removes the single folder that contains all entries, like in archives downloaded from GitHub.
+    pub strip_root_folder: bool,
+
+    /// Only the content of this archive folder is extracted, placed directly in the destination.
+    /// Applied after the root folder was stripped.
+    pub subtree: Option<String>,
+
+    /// Only entries matching this glob pattern are extracted.
+    /// It is matched against the path the entry will have relative to the destination.
+    pub pattern: Option<String>,
+
+    /// Archives exceeding these are rejected before anything is written.
+    pub limits: ZipLimits,
+
+    /// Records all created and overwritten files in a manifest at this path, to uninstall them later.
+    /// Overwritten originals are kept next to it. An existing manifest is extended.
+    pub manifest: Option<String>,
+}
+
+/// Progress of a running extraction, send to the frontend after every entry.
+#[derive(Serialize, Clone)]
+pub struct ExtractionProgress {
+    pub job_id: String,
+    pub entries_done: usize,
+    pub entries_total: usize,
+    pub bytes_written: u64,
+    pub entry: String,
+}
+
+/// Progress of the extraction, reported after every extracted entry.
+pub struct EntryProgress<'a> {
+    pub entries_done: usize,
+    pub entries_total: usize,
+    pub bytes_written: u64,
+    pub entry: &'a str,
+}
+
+struct PlannedEntry {
+    index: usize,
+    relative_path: PathBuf,
+    is_dir: bool,
+    unix_mode: Option<u32>,
+}
+
+/// Remembers everything an extraction created, so it can be removed again.
+/// Files that already existed are overwritten and are not part of the record,
+/// unless they were moved to a backup first.
+#[derive(Default)]
+struct ExtractionRecord {
+    created_files: Vec<PathBuf>,
+    created_dirs: Vec<PathBuf>,
+    replaced_files: Vec<(PathBuf, PathBuf)>, // (original, backup)
+}
+
+impl ExtractionRecord {
+    fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        let missing_dirs: Vec<&Path> = path.ancestors().take_while(|dir| !dir.exists()).collect();
+        for dir in missing_dirs.into_iter().rev() {
+            fs::create_dir(dir)?;
+            self.created_dirs.push(dir.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &Path) -> io::Result<File> {
+        let existed = path.exists();
+        let file = File::create(path)?;
+        if !existed {
+            self.created_files.push(path.to_path_buf());
+        }
+        Ok(file)
+    }
+
+    // moves the current file away, so that it can be restored on rollback
+    fn backup_file(&mut self, path: &Path, backup_path: &Path) -> io::Result<()> {
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(path, backup_path)?;
+        self.replaced_files
+            .push((path.to_path_buf(), backup_path.to_path_buf()));
+        Ok(())
+    }
+
+    // best effort, errors are ignored, since the original error is more important
+    // returns false if not all backups could be restored
+    fn rollback(self) -> bool {
+        for file in self.created_files.iter().rev() {
+            let _ = fs::remove_file(file);
+        }
+        let mut restored_all = true;
+        for (original, backup) in self.replaced_files.iter().rev() {
+            let _ = fs::remove_file(original);
+            if let Err(err) = fs::rename(backup, original) {
+                error!(
+                    "Failed to restore '{}' from backup '{}': {}",
+                    original.display(),
+                    backup.display(),
+                    err
+                );
+                restored_all = false;
+            }
+        }
+        for dir in self.created_dirs.iter().rev() {
+            let _ = fs::remove_dir(dir);
+        }
+        restored_all
+    }
+}
+
+fn copy_with_cancel<R: Read + ?Sized, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    cancel: &AtomicBool,
+    bytes_written: &mut u64,
+) -> Result<(), ZipSupportError> {
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ZipSupportError::Cancelled);
+        }
+        let read_bytes = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read_bytes) => read_bytes,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        writer.write_all(&buffer[..read_bytes])?;
+        *bytes_written += read_bytes as u64;
+    }
+}
+
+fn split_entry_name(name: &str) -> Vec<&str> {
+    name.split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect()
+}
+
+// files directly in the archive root or multiple root folders prevent stripping
+fn check_single_root_folder(archive: &ArchiveReader) -> Result<(), ZipSupportError> {
+    let mut root_folder: Option<&str> = None;
+    for entry in archive.entries() {
+        let components = split_entry_name(&entry.name);
+        let root = match components.first() {
+            Some(root) => *root,
+            None => continue,
+        };
+        if components.len() == 1 && !entry.is_dir {
+            return Err(ZipSupportError::Key("zip.extract.root.folder.missing"));
+        }
+        match root_folder {
+            None => root_folder = Some(root),
+            Some(folder) if folder == root => (),
+            Some(_) => return Err(ZipSupportError::Key("zip.extract.root.folder.missing")),
+        }
+    }
+    root_folder
+        .map(|_| ())
+        .ok_or(ZipSupportError::Key("zip.extract.root.folder.missing"))
+}
+
+// decides which entries are extracted and where they are placed
+fn plan_entries(
+    archive: &ArchiveReader,
+    options: &ExtractionOptions,
+) -> Result<Vec<PlannedEntry>, ZipSupportError> {
+    if options.strip_root_folder {
+        check_single_root_folder(archive)?;
+    }
+    let subtree = options
+        .subtree
+        .as_deref()
+        .map(split_entry_name)
+        .unwrap_or_default();
+    let glob_pattern = match options.pattern.as_deref() {
+        Some(pattern) if !pattern.is_empty() => Some(
+            GlobPattern::new(pattern)
+                .map_err(|_| ZipSupportError::Key("zip.extract.pattern.invalid"))?,
+        ),
+        _ => None,
+    };
+
+    let mut planned_entries = Vec::with_capacity(archive.entries().len());
+    for (index, entry) in archive.entries().iter().enumerate() {
+        let mut components = split_entry_name(&entry.name);
+        if options.strip_root_folder && !components.is_empty() {
+            components.remove(0);
+        }
+        if !components.starts_with(&subtree) {
+            continue;
+        }
+        let components = &components[subtree.len()..];
+        if components.is_empty() {
+            continue;
+        }
+        if let Some(glob_pattern) = &glob_pattern {
+            if !glob_pattern.matches_with(&components.join("/"), PATH_MATCH_OPTIONS) {
+                continue;
+            }
+        }
+        planned_entries.push(PlannedEntry {
+            index,
+            relative_path: components.iter().collect(),
+            is_dir: entry.is_dir,
+            unix_mode: entry.unix_mode,
+        });
+    }
+    Ok(planned_entries)
+}
+
+// moves the staged entries into the destination, the record allows to revert every step
+fn commit_staged_entries(
+    entries: &[PlannedEntry],
+    staging_dir: &Path,
+    dest: &Path,
+    backup_dir: &Path,
+    record: &mut ExtractionRecord,
+) -> io::Result<()> {
+    let mut committed_paths = HashSet::new();
+    for entry in entries {
+        // duplicated entries were already overwritten in the staging folder
+        if !committed_paths.insert(&entry.relative_path) {
+            continue;
+        }
+
+        let target_path = dest.join(&entry.relative_path);
+        if entry.is_dir {
+            record.create_dir_all(&target_path)?;
+            continue;
+        }
+        if let Some(parent) = target_path.parent() {
+            record.create_dir_all(parent)?;
+        }
+
+        let replaces_file = target_path.symlink_metadata().is_ok();
+        if replaces_file {
+            record.backup_file(&target_path, &backup_dir.join(&entry.relative_path))?;
+        }
+        fs::rename(staging_dir.join(&entry.relative_path), &target_path)?;
+        if !replaces_file {
+            record.created_files.push(target_path);
+        }
+    }
+    Ok(())
+}
+
+// the backup is only needed until the extraction succeeded or was reverted
+fn finish_backup(dest: &Path, backup_dir: &Path, restored_all: bool) {
+    if restored_all {
+        let _ = fs::remove_dir_all(backup_dir);
+    } else {
+        error!(
+            "Extraction into '{}' failed and could not be fully reverted, kept backup in '{}'.",
+            dest.display(),
+            backup_dir.display()
+        );
+    }
+}
+
+fn get_unique_folder_suffix() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{}", std::process::id(), nanos)
+}
+
+/// Extracts the planned entries of an archive.
+struct EntryExtractor<'a, F: FnMut(EntryProgress)> {
+    archive: &'a mut ArchiveReader,
+    planned_entries: Vec<PlannedEntry>,
+    cancel: &'a AtomicBool,
+    on_entry_done: F,
+}
+
+impl<'a, F: FnMut(EntryProgress)> EntryExtractor<'a, F> {
+    fn check_cancel(&self) -> Result<(), ZipSupportError> {
+        if self.cancel.load(Ordering::Relaxed) {
+            Err(ZipSupportError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    // the entries are read in the order of the archive, since not every format allows random access
+    // with a backup folder, existing files are moved there before they are overwritten
+    fn extract_entries(
+        &mut self,
+        dest: &Path,
+        record: &mut ExtractionRecord,
+        backup_dir: Option<&Path>,
+    ) -> Result<(), ZipSupportError> {
+        let entries_total = self.planned_entries.len();
+        let indices: Vec<usize> = self
+            .planned_entries
+            .iter()
+            .map(|entry| entry.index)
+            .collect();
+        let planned_entries: HashMap<usize, &PlannedEntry> = self
+            .planned_entries
+            .iter()
+            .map(|entry| (entry.index, entry))
+            .collect();
+        let cancel = self.cancel;
+        let on_entry_done = &mut self.on_entry_done;
+        let mut entries_done = 0;
+        let mut bytes_written: u64 = 0;
+        let mut written_paths = HashSet::new();
+
+        self.archive
+            .read_entries(&indices, |index, archive_entry, reader| {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(ZipSupportError::Cancelled);
+                }
+
+                let entry = planned_entries[&index];
+                let out_path = dest.join(&entry.relative_path);
+
+                if entry.is_dir {
+                    record.create_dir_all(&out_path)?;
+                } else {
+                    if let Some(parent) = out_path.parent() {
+                        record.create_dir_all(parent)?;
+                    }
+                    // duplicated entries overwrite the file of this extraction, not the original
+                    let is_first_write = written_paths.insert(&entry.relative_path);
+                    if let Some(backup_dir) = backup_dir {
+                        if is_first_write && out_path.is_file() {
+                            record
+                                .backup_file(&out_path, &backup_dir.join(&entry.relative_path))?;
+                        }
+                    }
+                    let mut out_file = record.create_file(&out_path)?;
+                    copy_with_cancel(reader, &mut out_file, cancel, &mut bytes_written)?;
+                }
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    if let Some(mode) = entry.unix_mode {
+                        fs::set_permissions(&out_path, fs::Permissions::from_mode(mode))?;
+                    }
+                }
+
+                entries_done += 1;
+                on_entry_done(EntryProgress {
+                    entries_done,
+                    entries_total,
+                    bytes_written,
+                    entry: &archive_entry.name,
+                });
+                Ok(())
+            })
+    }
+
+    fn extract_transactional(
+        &mut self,
+        dest: &Path,
+        record: &mut ExtractionRecord,
+        staging_dir: &Path,
+        backup_dir: &Path,
+    ) -> Result<(), ZipSupportError> {
+        record.create_dir_all(dest)?;
+        fs::create_dir(staging_dir)?;
+
+        // the staging folder is removed as a whole, so its content is not recorded
+        self.extract_entries(staging_dir, &mut ExtractionRecord::default(), None)?;
+
+        // last chance to cancel, the commit itself should only consist of fast renames
+        self.check_cancel()?;
+        commit_staged_entries(&self.planned_entries, staging_dir, dest, backup_dir, record)?;
+        Ok(())
+    }
+
+    /// Extracts the entries into a staging folder inside the destination and swaps the files in afterwards.
+    /// Overwritten files are moved into a backup folder during the swap.
+    /// On error or cancel, the previous state is restored.
+    /// Should restoring fail, the backup folder is kept.
+    fn extract_with_transaction(&mut self, dest: &Path) -> Result<(), ZipSupportError> {
+        let folder_suffix = get_unique_folder_suffix();
+        let staging_dir = dest.join(format!("{}{}", STAGING_FOLDER_PREFIX, folder_suffix));
+        let backup_dir = dest.join(format!("{}{}", BACKUP_FOLDER_PREFIX, folder_suffix));
+
+        let mut record = ExtractionRecord::default();
+        let extract_result =
+            self.extract_transactional(dest, &mut record, &staging_dir, &backup_dir);
+        let _ = fs::remove_dir_all(&staging_dir);
+        finish_backup(
+            dest,
+            &backup_dir,
+            extract_result.is_ok() || record.rollback(),
+        );
+        extract_result
+    }
+
+    /// Extracts the entries into the destination, overwriting existing files.
+    /// Overwritten files are moved into a backup folder right before they are written.
+    /// If cancelled or on error, all files and folders created by this extraction are removed again
+    /// and the overwritten files are restored.
+    /// Should restoring fail, the backup folder is kept.
+    fn extract_with_rollback(&mut self, dest: &Path) -> Result<(), ZipSupportError> {
+        let backup_dir = dest.join(format!(
+            "{}{}",
+            BACKUP_FOLDER_PREFIX,
+            get_unique_folder_suffix()
+        ));
+        let mut record = ExtractionRecord::default();
+        let extract_result = self.extract_entries(dest, &mut record, Some(&backup_dir));
+        finish_backup(
+            dest,
+            &backup_dir,
+            extract_result.is_ok() || record.rollback(),
+        );
+        extract_result
+    }
+}
+
+fn prepare_manifest(
+    manifest_path: &Path,
+    dest: &Path,
+    planned_entries: &[PlannedEntry],
+) -> Result<ManifestRecorder, ZipSupportError> {
+    let (dirs, files): (Vec<&PlannedEntry>, Vec<&PlannedEntry>) =
+        planned_entries.iter().partition(|entry| entry.is_dir);
+    let to_paths = |entries: Vec<&PlannedEntry>| -> Vec<PathBuf> {
+        entries
+            .into_iter()
+            .map(|entry| entry.relative_path.clone())
+            .collect()
+    };
+    ManifestRecorder::prepare(manifest_path, dest, &to_paths(files), &to_paths(dirs))
+}
+
+pub fn extract<F: FnMut(EntryProgress)>(
+    archive: &mut ArchiveReader,
+    dest: &Path,
+    options: &ExtractionOptions,
+    cancel: &AtomicBool,
+    on_entry_done: F,
+) -> Result<(), ZipSupportError> {
+    // nothing is written if a single entry is unsafe or the archive is too large
+    safety::validate_archive(archive)?;
+    limits::check_archive(archive, &options.limits)?;
+
+    let planned_entries = plan_entries(archive, options)?;
+    let manifest_recorder = match &options.manifest {
+        Some(manifest_path) => Some(prepare_manifest(
+            Path::new(manifest_path),
+            dest,
+            &planned_entries,
+        )?),
+        None => None,
+    };
+
+    let mut extractor = EntryExtractor {
+        archive,
+        planned_entries,
+        cancel,
+        on_entry_done,
+    };
+    let extract_result = if options.transactional {
+        extractor.extract_with_transaction(dest)
+    } else {
+        extractor.extract_with_rollback(dest)
+    };
+    match (manifest_recorder, extract_result) {
+        (Some(manifest_recorder), Ok(())) => manifest_recorder.finish(),
+        (Some(manifest_recorder), Err(err)) => {
+            manifest_recorder.discard();
+            Err(err)
+        }
+        (None, extract_result) => extract_result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip_support::manifest::{uninstall, UninstallOptions};
+    use std::io::Cursor;
+    use zip::{write::FileOptions, ZipWriter};
+
+    fn open_archive(entries: &[(&str, &str)]) -> ArchiveReader {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        ArchiveReader::open(writer.finish().unwrap()).unwrap()
+    }
+
+    fn extract_with_manifest(
+        dest: &Path,
+        manifest: &Path,
+        entries: &[(&str, &str)],
+        cancel_after: Option<usize>,
+    ) -> Result<(), ZipSupportError> {
+        let options = ExtractionOptions {
+            manifest: Some(manifest.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        extract_with_options(dest, &options, entries, cancel_after)
+    }
+
+    fn extract_with_options(
+        dest: &Path,
+        options: &ExtractionOptions,
+        entries: &[(&str, &str)],
+        cancel_after: Option<usize>,
+    ) -> Result<(), ZipSupportError> {
+        let cancel = AtomicBool::new(false);
+        extract(
+            &mut open_archive(entries),
+            dest,
+            options,
+            &cancel,
+            |progress| {
+                if cancel_after == Some(progress.entries_done) {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+        )
+    }
+
+    #[test]
+    fn cancel_restores_overwritten_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user").unwrap();
+
+        // the duplicate must not replace the backup of the original
+        let entries = [("a.txt", "new"), ("a.txt", "newer"), ("b.txt", "new")];
+        let result = extract_with_options(&dest, &ExtractionOptions::default(), &entries, Some(2));
+        assert!(matches!(result, Err(ZipSupportError::Cancelled)));
+
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "user");
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 1); // no backup folder left
+    }
+
+    #[test]
+    fn extraction_overwrites_without_leftovers() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user").unwrap();
+
+        let entries = [("a.txt", "new"), ("b.txt", "new")];
+        extract_with_options(&dest, &ExtractionOptions::default(), &entries, None).unwrap();
+
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn failed_extraction_restores_overwritten_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        let manifest = temp.path().join("manifest.json");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user").unwrap();
+
+        let entries = [("a.txt", "new"), ("b.txt", "new"), ("c.txt", "new")];
+        let result = extract_with_manifest(&dest, &manifest, &entries, Some(2));
+        assert!(matches!(result, Err(ZipSupportError::Cancelled)));
+
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "user");
+        assert!(!dest.join("b.txt").exists());
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1); // only the destination
+    }
+
+    #[test]
+    fn failed_extraction_restores_files_of_previous_extractions() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        let manifest = temp.path().join("manifest.json");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user").unwrap();
+        extract_with_manifest(&dest, &manifest, &[("a.txt", "first")], None).unwrap();
+
+        let entries = [("a.txt", "second"), ("b.txt", "second")];
+        assert!(extract_with_manifest(&dest, &manifest, &entries, Some(1)).is_err());
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "first");
+        assert!(!dest.join("b.txt").exists());
+
+        // the original from before the first extraction is still kept
+        let result = uninstall(&manifest, &UninstallOptions::default()).unwrap();
+        assert_eq!(result.restored, vec!["a.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "user");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::{Duration, Instant};
+
+// ids need to stay below 2^53 to survive the conversion to a javascript number,
+// so 20 bits are used for the slot index and at most 32 bits for the generation
+const INDEX_BITS: u32 = 20;
+const INDEX_MASK: u64 = (1 << INDEX_BITS) - 1;
+const MAX_SLOTS: usize = 1 << INDEX_BITS;
+
+struct Handle<T> {
+    value: T,
+    owner: String,
+    last_access: Instant,
+}
+
+struct Slot<T> {
+    generation: u32,
+    handle: Option<Handle<T>>,
+}
+
+/// Stores values under generational ids.
+/// Every slot counts up its generation when freed, so ids of removed values never become valid again,
+/// even if the slot is reused.
+/// Every value remembers the window label that created it and the last time it was accessed,
+/// so leftovers can be removed if the window is gone or the handle is no longer used.
+pub struct HandleRegistry<T> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<usize>,
+}
+
+impl<T> HandleRegistry<T> {
+    pub fn new() -> HandleRegistry<T> {
+        HandleRegistry {
+            slots: Vec::new(),
+            free_indices: Vec::new(),
+        }
+    }
+
+    fn to_id(index: usize, generation: u32) -> u64 {
+        (u64::from(generation) << INDEX_BITS) | index as u64
+    }
+
+    fn get_handle_mut(&mut self, id: u64) -> Option<&mut Handle<T>> {
+        let index = (id & INDEX_MASK) as usize;
+        let generation = u32::try_from(id >> INDEX_BITS).ok()?;
+        self.slots
+            .get_mut(index)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.handle.as_mut())
+    }
+
+    /// Returns None if all slots are taken.
+    pub fn insert(&mut self, owner: &str, value: T) -> Option<u64> {
+        let index = match self.free_indices.pop() {
+            Some(index) => index,
+            None if self.slots.len() < MAX_SLOTS => {
+                // generation starts at 1, so 0 is never a valid id
+                self.slots.push(Slot {
+                    generation: 1,
+                    handle: None,
+                });
+                self.slots.len() - 1
+            }
+            None => return None,
+        };
+        let slot = &mut self.slots[index];
+        slot.handle = Some(Handle {
+            value,
+            owner: owner.to_string(),
+            last_access: Instant::now(),
+        });
+        Some(Self::to_id(index, slot.generation))
+    }
+
+    // counts as access, so it resets the idle time
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut T> {
+        let handle = self.get_handle_mut(id)?;
+        handle.last_access = Instant::now();
+        Some(&mut handle.value)
+    }
+
+    fn remove_at(&mut self, index: usize) -> Option<T> {
+        let slot = &mut self.slots[index];
+        let handle = slot.handle.take()?;
+        slot.generation = match slot.generation.wrapping_add(1) {
+            0 => 1,
+            generation => generation,
+        };
+        self.free_indices.push(index);
+        Some(handle.value)
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<T> {
+        self.get_handle_mut(id)?;
+        self.remove_at((id & INDEX_MASK) as usize)
+    }
+
+    fn remove_where<F: Fn(&Handle<T>) -> bool>(&mut self, predicate: F) -> Vec<T> {
+        let indices: Vec<usize> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.handle.as_ref().is_some_and(&predicate))
+            .map(|(index, _)| index)
+            .collect();
+        indices
+            .into_iter()
+            .filter_map(|index| self.remove_at(index))
+            .collect()
+    }
+
+    pub fn remove_owned_by(&mut self, owner: &str) -> Vec<T> {
+        self.remove_where(|handle| handle.owner == owner)
+    }
+
+    pub fn remove_idle(&mut self, timeout: Duration) -> Vec<T> {
+        self.remove_where(|handle| handle.last_access.elapsed() >= timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn ids_of_removed_values_stay_invalid() {
+        let mut registry = HandleRegistry::new();
+        let first_id = registry.insert("main", "first").unwrap();
+        assert_eq!(registry.remove(first_id), Some("first"));
+        assert_eq!(registry.remove(first_id), None);
+
+        // the slot is reused with the next generation
+        let second_id = registry.insert("main", "second").unwrap();
+        assert_eq!(first_id & INDEX_MASK, second_id & INDEX_MASK);
+        assert_ne!(first_id, second_id);
+        assert_eq!(registry.get_mut(first_id), None);
+        assert_eq!(registry.get_mut(second_id), Some(&mut "second"));
+    }
+
+    #[test]
+    fn rejects_unknown_ids() {
+        let mut registry = HandleRegistry::new();
+        let id = registry.insert("main", 1).unwrap();
+        assert_ne!(id, 0);
+        assert_eq!(registry.get_mut(0), None);
+        assert_eq!(registry.get_mut(id + 1), None);
+        assert_eq!(registry.get_mut(id + (1 << INDEX_BITS)), None);
+        assert_eq!(registry.get_mut(u64::MAX), None);
+    }
+
+    #[test]
+    fn generation_skips_zero_on_overflow() {
+        let mut registry = HandleRegistry::new();
+        let first_id = registry.insert("main", 1).unwrap();
+        registry.slots[0].generation = u32::MAX;
+        let last_id = HandleRegistry::<i32>::to_id(0, u32::MAX);
+        assert_eq!(registry.remove(last_id), Some(1));
+        assert_eq!(registry.slots[0].generation, 1);
+        assert_eq!(registry.insert("main", 2), Some(first_id));
+    }
+
+    #[test]
+    fn ids_fit_into_javascript_numbers() {
+        let id = HandleRegistry::<i32>::to_id(MAX_SLOTS - 1, u32::MAX);
+        assert!(id < (1 << 53));
+    }
+
+    #[test]
+    fn removes_handles_of_an_owner() {
+        let mut registry = HandleRegistry::new();
+        let main_id = registry.insert("main", "main").unwrap();
+        registry.insert("other", "other a").unwrap();
+        registry.insert("other", "other b").unwrap();
+
+        let mut removed = registry.remove_owned_by("other");
+        removed.sort();
+        assert_eq!(removed, vec!["other a", "other b"]);
+        assert!(registry.remove_owned_by("other").is_empty());
+        assert_eq!(registry.get_mut(main_id), Some(&mut "main"));
+    }
+
+    #[test]
+    fn removes_only_idle_handles() {
+        let mut registry = HandleRegistry::new();
+        let idle_id = registry.insert("main", "idle").unwrap();
+        let used_id = registry.insert("main", "used").unwrap();
+        assert!(registry.remove_idle(Duration::from_secs(60)).is_empty());
+
+        thread::sleep(Duration::from_millis(50));
+        // access resets the idle time
+        registry.get_mut(used_id).unwrap();
+        assert_eq!(
+            registry.remove_idle(Duration::from_millis(25)),
+            vec!["idle"]
+        );
+        assert_eq!(registry.get_mut(idle_id), None);
+        assert_eq!(registry.get_mut(used_id), Some(&mut "used"));
+        assert_eq!(registry.remove_idle(Duration::ZERO), vec!["used"]);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::io::{self, Read};
+
+use super::{
+    archive::{ArchiveEntry, ArchiveReader},
+    error::ZipSupportError,
+};
+use crate::constants::{
+    ZIP_LIMIT_MAX_ENTRIES, ZIP_LIMIT_MAX_ENTRY_BYTES, ZIP_LIMIT_MAX_RATIO,
+    ZIP_LIMIT_MAX_TOTAL_BYTES,
+};
+
+// small entries may compress extremely well without being dangerous, like files full of zeros
+const RATIO_CHECK_MIN_SIZE: u64 = 1024 * 1024;
+
+/// Limits for the content of an archive, to reject archives that would fill the disk or memory.
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZipLimits {
+    /// Uncompressed size of all entries together.
+    pub max_total_bytes: u64,
+    pub max_entries: usize,
+    /// Uncompressed size divided by compressed size, only checked for entries above 1MB.
+    pub max_ratio: u64,
+    /// Uncompressed size of a single entry.
+    pub max_entry_bytes: u64,
+}
+
+impl Default for ZipLimits {
+    fn default() -> Self {
+        ZipLimits {
+            max_total_bytes: ZIP_LIMIT_MAX_TOTAL_BYTES,
+            max_entries: ZIP_LIMIT_MAX_ENTRIES,
+            max_ratio: ZIP_LIMIT_MAX_RATIO,
+            max_entry_bytes: ZIP_LIMIT_MAX_ENTRY_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("The archive exceeds a limit ({code}).")]
+pub struct LimitExceeded {
+    pub code: &'static str,
+    pub entry: Option<String>,
+}
+
+fn limit_exceeded(code: &'static str, entry: Option<&str>) -> ZipSupportError {
+    ZipSupportError::Limit(LimitExceeded {
+        code,
+        entry: entry.map(String::from),
+    })
+}
+
+/// Checks the sizes stored in the archive, before anything is decompressed.
+/// The stored sizes might lie, so every read entry also needs to be wrapped in a [LimitedReader].
+pub fn check_archive(archive: &ArchiveReader, limits: &ZipLimits) -> Result<(), ZipSupportError> {
+    check_entries(archive.entries(), archive.size(), limits)
+}
+
+// if entries are not compressed separately, the whole archive is the upper bound of their compressed size
+pub fn check_entries(
+    entries: &[ArchiveEntry],
+    archive_size: u64,
+    limits: &ZipLimits,
+) -> Result<(), ZipSupportError> {
+    if entries.len() > limits.max_entries {
+        return Err(limit_exceeded("zip.limit.entries", None));
+    }
+    let mut total_bytes: u64 = 0;
+    for entry in entries {
+        let size = entry.size;
+        if size > limits.max_entry_bytes {
+            return Err(limit_exceeded("zip.limit.entry.size", Some(&entry.name)));
+        }
+        let compressed_size = entry.compressed_size.unwrap_or(archive_size);
+        if size > RATIO_CHECK_MIN_SIZE && size / compressed_size.max(1) > limits.max_ratio {
+            return Err(limit_exceeded("zip.limit.ratio", Some(&entry.name)));
+        }
+        total_bytes = total_bytes.saturating_add(size);
+        if total_bytes > limits.max_total_bytes {
+            return Err(limit_exceeded("zip.limit.total.size", None));
+        }
+    }
+    Ok(())
+}
+
+/// Fails if the entry decompresses to more bytes than the archive claims,
+/// so the checked sizes can be trusted.
+pub struct LimitedReader<R: Read> {
+    inner: R,
+    remaining: u64,
+    entry: String,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub fn new(inner: R, declared_size: u64, entry: &str) -> LimitedReader<R> {
+        LimitedReader {
+            inner,
+            remaining: declared_size,
+            entry: entry.to_string(),
+        }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_bytes = self.inner.read(buf)?;
+        match self.remaining.checked_sub(read_bytes as u64) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                Ok(read_bytes)
+            }
+            // the error is turned back into the limit error by the zip support error
+            None => Err(io::Error::new(
+                io::ErrorKind::Other,
+                LimitExceeded {
+                    code: "zip.limit.entry.size",
+                    entry: Some(self.entry.clone()),
+                },
+            )),
+        }
+    }
+}
@@ -0,0 +1,456 @@
+use log::error;
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashSet},
+    fs,
+    io::{self, BufReader},
+    path::{Component, Path, PathBuf},
+};
+
+use super::error::ZipSupportError;
+use crate::{
+    file_support::safe_write::write_atomic, hash_utils::get_sha256_of_path, utils::with_suffix,
+};
+
+const MANIFEST_VERSION: u32 = 1;
+const ORIGINALS_FOLDER_SUFFIX: &str = ".originals";
+
+/// A file written by an extraction.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ManifestFile {
+    /// Relative to the destination, with slashes.
+    pub path: String,
+    pub sha256: String,
+    /// Set if the file existed before, the original is kept next to the manifest.
+    pub original_sha256: Option<String>,
+}
+
+/// Everything extractions into a destination created or overwritten, to uninstall them again.
+/// Later extractions with the same manifest are merged, so the originals are always from before the first.
+#[derive(Serialize, Deserialize)]
+pub struct ExtractionManifest {
+    pub version: u32,
+    pub dest: PathBuf,
+    pub files: Vec<ManifestFile>,
+    /// Folders that did not exist before, relative to the destination.
+    pub created_dirs: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UninstallOptions {
+    /// Also removes or restores files that were changed after the extraction.
+    pub force: bool,
+}
+
+/// What an uninstall did, paths are relative to the destination.
+#[derive(Serialize, Default)]
+pub struct UninstallResult {
+    pub removed: Vec<String>,
+    pub restored: Vec<String>,
+    /// Already gone, restored originals are put back anyway.
+    pub missing: Vec<String>,
+    /// Changed after the extraction and kept, together with their originals.
+    pub modified: Vec<String>,
+    /// Could not be removed or restored, like locked files.
+    /// They stay in the manifest, so the uninstall can be repeated.
+    pub failed: Vec<String>,
+}
+
+fn get_originals_folder(manifest_path: &Path) -> PathBuf {
+    with_suffix(manifest_path, ORIGINALS_FOLDER_SUFFIX)
+}
+
+fn to_slash_string(path: &Path) -> String {
+    path.to_slash_lossy().to_string()
+}
+
+// manifests are files on disk, so their paths are not trusted
+fn to_safe_relative_path(path: &str) -> Result<PathBuf, ZipSupportError> {
+    let relative_path = PathBuf::from(path);
+    let is_safe = relative_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_safe || path.is_empty() {
+        return Err(ZipSupportError::Key("zip.manifest.path.invalid"));
+    }
+    Ok(relative_path)
+}
+
+fn read_manifest(manifest_path: &Path) -> Result<ExtractionManifest, ZipSupportError> {
+    let file = fs::File::open(manifest_path)?;
+    let manifest: ExtractionManifest = serde_json::from_reader(BufReader::new(file))
+        .map_err(|_| ZipSupportError::Key("zip.manifest.invalid"))?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(ZipSupportError::Key("zip.manifest.version.unsupported"));
+    }
+    Ok(manifest)
+}
+
+// a half written manifest would lose the originals, so it is replaced in one step
+fn write_manifest(manifest_path: &Path, manifest: &ExtractionManifest) -> io::Result<()> {
+    let content = serde_json::to_vec_pretty(manifest)?;
+    write_atomic(manifest_path, &content)?;
+    Ok(())
+}
+
+// originals might be on another drive than the destination
+fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    fs::copy(source, target)?;
+    fs::remove_file(source)
+}
+
+fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, target).map(|_| ())
+}
+
+/// Prepared before an extraction, keeps copies of all files the extraction will overwrite.
+pub struct ManifestRecorder {
+    manifest_path: PathBuf,
+    dest: PathBuf,
+    previous: Option<ExtractionManifest>,
+    files: Vec<(PathBuf, Option<String>)>, // (relative path, sha256 of the original)
+    created_dirs: Vec<PathBuf>,
+    copied_originals: Vec<PathBuf>,
+}
+
+impl ManifestRecorder {
+    /// Takes the relative paths of all files and folders the extraction will write.
+    pub fn prepare(
+        manifest_path: &Path,
+        dest: &Path,
+        files: &[PathBuf],
+        dirs: &[PathBuf],
+    ) -> Result<ManifestRecorder, ZipSupportError> {
+        let previous = if manifest_path.exists() {
+            let previous = read_manifest(manifest_path)?;
+            if previous.dest != dest {
+                return Err(ZipSupportError::Key("zip.manifest.dest.mismatch"));
+            }
+            Some(previous)
+        } else {
+            None
+        };
+
+        let mut created_dirs = BTreeSet::new();
+        let parents = files.iter().filter_map(|file| file.parent());
+        for dir in dirs.iter().map(PathBuf::as_path).chain(parents) {
+            for ancestor in dir.ancestors() {
+                if !ancestor.as_os_str().is_empty() && !dest.join(ancestor).exists() {
+                    created_dirs.insert(ancestor.to_path_buf());
+                }
+            }
+        }
+
+        let mut recorder = ManifestRecorder {
+            manifest_path: manifest_path.to_path_buf(),
+            dest: dest.to_path_buf(),
+            previous,
+            files: Vec::new(),
+            created_dirs: created_dirs.into_iter().collect(),
+            copied_originals: Vec::new(),
+        };
+        if let Err(err) = recorder.keep_originals(files) {
+            recorder.discard();
+            return Err(err);
+        }
+        Ok(recorder)
+    }
+
+    // files recorded by a previous extraction already have their original
+    fn keep_originals(&mut self, files: &[PathBuf]) -> Result<(), ZipSupportError> {
+        let previous_files: HashSet<&str> = self
+            .previous
+            .iter()
+            .flat_map(|previous| previous.files.iter())
+            .map(|file| file.path.as_str())
+            .collect();
+        let originals_folder = get_originals_folder(&self.manifest_path);
+
+        let mut seen_files = HashSet::new();
+        for relative_path in files {
+            if !seen_files.insert(relative_path) {
+                continue;
+            }
+            let target_path = self.dest.join(relative_path);
+            if !target_path.is_file() {
+                self.files.push((relative_path.clone(), None));
+                continue;
+            }
+            let original_sha256 =
+                if previous_files.contains(to_slash_string(relative_path).as_str()) {
+                    None
+                } else {
+                    let original_path = originals_folder.join(relative_path);
+                    copy_file(&target_path, &original_path)?;
+                    self.copied_originals.push(original_path.clone());
+                    Some(get_sha256_of_path(&original_path)?)
+                };
+            self.files.push((relative_path.clone(), original_sha256));
+        }
+        Ok(())
+    }
+
+    /// Removes the originals copied for a failed extraction.
+    pub fn discard(self) {
+        for original in &self.copied_originals {
+            if let Err(err) = fs::remove_file(original) {
+                error!(
+                    "Failed to remove kept original '{}': {}",
+                    original.display(),
+                    err
+                );
+            }
+        }
+        if self.previous.is_none() {
+            let _ = fs::remove_dir_all(get_originals_folder(&self.manifest_path));
+        }
+    }
+
+    /// Hashes the extracted files and writes the manifest, merged with the previous one.
+    pub fn finish(self) -> Result<(), ZipSupportError> {
+        let mut manifest = self.previous.unwrap_or(ExtractionManifest {
+            version: MANIFEST_VERSION,
+            dest: self.dest.clone(),
+            files: Vec::new(),
+            created_dirs: Vec::new(),
+        });
+
+        for (relative_path, original_sha256) in &self.files {
+            let path = to_slash_string(relative_path);
+            let sha256 = get_sha256_of_path(&self.dest.join(relative_path))?;
+            match manifest.files.iter_mut().find(|file| file.path == path) {
+                Some(file) => file.sha256 = sha256,
+                None => manifest.files.push(ManifestFile {
+                    path,
+                    sha256,
+                    original_sha256: original_sha256.clone(),
+                }),
+            }
+        }
+        for dir in &self.created_dirs {
+            let dir = to_slash_string(dir);
+            if !manifest.created_dirs.contains(&dir) {
+                manifest.created_dirs.push(dir);
+            }
+        }
+        write_manifest(&self.manifest_path, &manifest)?;
+        Ok(())
+    }
+}
+
+/// Returns the destination the manifest was written for, to check if it may be changed.
+pub fn get_manifest_dest(manifest_path: &Path) -> Result<PathBuf, ZipSupportError> {
+    Ok(read_manifest(manifest_path)?.dest)
+}
+
+enum FileUninstall {
+    Removed,
+    Restored { missing: bool },
+    Missing,
+    Modified,
+}
+
+fn uninstall_file(
+    target_path: &Path,
+    original_path: &Path,
+    file: &ManifestFile,
+    options: &UninstallOptions,
+) -> io::Result<FileUninstall> {
+    let exists = target_path.is_file();
+    if exists {
+        let unchanged = get_sha256_of_path(target_path)? == file.sha256;
+        if !unchanged && !options.force {
+            return Ok(FileUninstall::Modified);
+        }
+        fs::remove_file(target_path)?;
+    }
+
+    if file.original_sha256.is_some() {
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_file(original_path, target_path)?;
+        Ok(FileUninstall::Restored { missing: !exists })
+    } else if exists {
+        Ok(FileUninstall::Removed)
+    } else {
+        Ok(FileUninstall::Missing)
+    }
+}
+
+/// Removes the files of the manifest and puts back the originals they replaced.
+/// Created folders are only removed if they are empty afterwards.
+/// The manifest and the originals are removed, unless modified or failed files were kept.
+/// Then the manifest only contains the kept files.
+pub fn uninstall(
+    manifest_path: &Path,
+    options: &UninstallOptions,
+) -> Result<UninstallResult, ZipSupportError> {
+    let manifest = read_manifest(manifest_path)?;
+    let originals_folder = get_originals_folder(manifest_path);
+    let mut result = UninstallResult::default();
+
+    // checked first, so an invalid manifest changes nothing
+    let files = manifest
+        .files
+        .iter()
+        .map(|file| Ok((to_safe_relative_path(&file.path)?, file)))
+        .collect::<Result<Vec<_>, ZipSupportError>>()?;
+    let created_dirs = manifest
+        .created_dirs
+        .iter()
+        .map(|dir| to_safe_relative_path(dir))
+        .collect::<Result<Vec<_>, ZipSupportError>>()?;
+    for (relative_path, file) in &files {
+        if file.original_sha256.is_some() && !originals_folder.join(relative_path).is_file() {
+            return Err(ZipSupportError::Key("zip.manifest.original.missing"));
+        }
+    }
+
+    // failing files are kept in the manifest, so finished files are not tried again
+    let mut kept_files = Vec::new();
+    for (relative_path, file) in files {
+        let target_path = manifest.dest.join(&relative_path);
+        let original_path = originals_folder.join(&relative_path);
+        match uninstall_file(&target_path, &original_path, file, options) {
+            Ok(FileUninstall::Removed) => result.removed.push(file.path.clone()),
+            Ok(FileUninstall::Restored { missing }) => {
+                if missing {
+                    result.missing.push(file.path.clone());
+                }
+                result.restored.push(file.path.clone());
+            }
+            Ok(FileUninstall::Missing) => result.missing.push(file.path.clone()),
+            Ok(FileUninstall::Modified) => {
+                result.modified.push(file.path.clone());
+                kept_files.push(file.clone());
+            }
+            Err(err) => {
+                error!("Failed to uninstall '{}': {}", target_path.display(), err);
+                result.failed.push(file.path.clone());
+                kept_files.push(file.clone());
+            }
+        }
+    }
+
+    // deepest folders first, so their parents can be empty afterwards
+    let mut created_dirs = created_dirs;
+    created_dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+    for dir in created_dirs {
+        let _ = fs::remove_dir(manifest.dest.join(dir));
+    }
+
+    // only the kept files remain, so a forced uninstall can finish later
+    if kept_files.is_empty() {
+        let _ = fs::remove_dir_all(&originals_folder);
+        fs::remove_file(manifest_path)?;
+    } else {
+        write_manifest(
+            manifest_path,
+            &ExtractionManifest {
+                files: kept_files,
+                ..manifest
+            },
+        )?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // records the files as if an extraction had written them
+    fn record(dest: &Path, manifest_path: &Path, files: &[(&str, &str)]) {
+        let relative_paths: Vec<PathBuf> =
+            files.iter().map(|(path, _)| PathBuf::from(path)).collect();
+        let recorder =
+            ManifestRecorder::prepare(manifest_path, dest, &relative_paths, &[]).unwrap();
+        for (path, content) in files {
+            let path = dest.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        recorder.finish().unwrap();
+    }
+
+    #[test]
+    fn uninstall_removes_and_restores() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        let manifest_path = temp.path().join("manifest.json");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user").unwrap();
+        record(
+            &dest,
+            &manifest_path,
+            &[("a.txt", "new"), ("folder/b.txt", "new")],
+        );
+
+        let result = uninstall(&manifest_path, &UninstallOptions::default()).unwrap();
+        assert_eq!(result.restored, vec!["a.txt"]);
+        assert_eq!(result.removed, vec!["folder/b.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "user");
+        assert!(!dest.join("folder").exists());
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn uninstall_keeps_modified_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        let manifest_path = temp.path().join("manifest.json");
+        fs::create_dir(&dest).unwrap();
+        record(&dest, &manifest_path, &[("a.txt", "new"), ("b.txt", "new")]);
+        fs::write(dest.join("a.txt"), "changed").unwrap();
+
+        let result = uninstall(&manifest_path, &UninstallOptions::default()).unwrap();
+        assert_eq!(result.modified, vec!["a.txt"]);
+        assert_eq!(result.removed, vec!["b.txt"]);
+        assert_eq!(read_manifest(&manifest_path).unwrap().files.len(), 1);
+
+        let result = uninstall(&manifest_path, &UninstallOptions { force: true }).unwrap();
+        assert_eq!(result.removed, vec!["a.txt"]);
+        assert!(!manifest_path.exists());
+    }
+
+    #[test]
+    fn failed_files_stay_in_the_manifest() {
+        let temp = tempfile::tempdir().unwrap();
+        let dest = temp.path().join("dest");
+        let manifest_path = temp.path().join("manifest.json");
+        fs::create_dir(&dest).unwrap();
+        fs::write(dest.join("a.txt"), "user a").unwrap();
+        fs::write(dest.join("b.txt"), "user b").unwrap();
+        record(&dest, &manifest_path, &[("a.txt", "new"), ("b.txt", "new")]);
+
+        // a folder in the way can not be replaced by the original
+        fs::remove_file(dest.join("a.txt")).unwrap();
+        fs::create_dir(dest.join("a.txt")).unwrap();
+        fs::write(dest.join("a.txt/blocker"), "").unwrap();
+
+        let result = uninstall(&manifest_path, &UninstallOptions::default()).unwrap();
+        assert_eq!(result.failed, vec!["a.txt"]);
+        assert_eq!(result.restored, vec!["b.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "user b");
+        let manifest = read_manifest(&manifest_path).unwrap();
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.files[0].path, "a.txt");
+
+        // the next try finishes once the problem is gone
+        fs::remove_dir_all(dest.join("a.txt")).unwrap();
+        let result = uninstall(&manifest_path, &UninstallOptions::default()).unwrap();
+        assert_eq!(result.restored, vec!["a.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "user a");
+        assert!(!manifest_path.exists());
+    }
+}
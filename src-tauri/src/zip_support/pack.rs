@@ -0,0 +1,324 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tauri::scope::GlobPattern;
+
+use super::{error::ZipSupportError, write_options::ZipWriterOptions, ZipWriterHelper};
+use crate::constants::PATH_MATCH_OPTIONS;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PackOptions {
+    /// Only files matching one of these globs are packed. Packs everything if empty.
+    pub include: Vec<String>,
+
+    /// Files and folders matching one of these globs are skipped, like "**/.cache" or "**/*.log".
+    pub exclude: Vec<String>,
+
+    /// Used for every packed entry.
+    #[serde(flatten)]
+    pub writer: ZipWriterOptions,
+}
+
+/// Progress of a running pack, send to the frontend after every file.
+#[derive(Serialize, Clone)]
+pub struct PackProgress {
+    pub job_id: String,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_written: u64,
+    pub entry: String,
+}
+
+/// Progress of the pack, reported after every packed file.
+pub struct FileProgress<'a> {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_written: u64,
+    pub entry: &'a str,
+}
+
+struct PackFilter {
+    include: Vec<GlobPattern>,
+    exclude: Vec<GlobPattern>,
+}
+
+impl PackFilter {
+    fn new(options: &PackOptions) -> Result<PackFilter, ZipSupportError> {
+        let to_patterns = |globs: &[String]| -> Result<Vec<GlobPattern>, ZipSupportError> {
+            globs
+                .iter()
+                .map(|glob| GlobPattern::new(glob))
+                .collect::<Result<_, _>>()
+                .map_err(|_| ZipSupportError::Key("zip.pack.pattern.invalid"))
+        };
+        Ok(PackFilter {
+            include: to_patterns(&options.include)?,
+            exclude: to_patterns(&options.exclude)?,
+        })
+    }
+
+    fn is_excluded(&self, relative_path: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_with(relative_path, PATH_MATCH_OPTIONS))
+    }
+
+    fn is_included(&self, relative_path: &str) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches_with(relative_path, PATH_MATCH_OPTIONS))
+    }
+}
+
+struct PackedFile {
+    path: PathBuf,
+    entry_name: String,
+    is_dir: bool,
+}
+
+// symlinks are skipped, so nothing outside the source folder is packed
+// excluded folders are not entered at all, empty folders are packed as folder entries
+// like when listing folders, all other folders are entered, but only allowed entries are packed
+fn collect_files(
+    folder: &Path,
+    relative_folder: &str,
+    filter: &PackFilter,
+    is_allowed: &dyn Fn(&Path) -> bool,
+    skip: &[PathBuf],
+    files: &mut Vec<PackedFile>,
+) -> Result<(), io::Error> {
+    let mut dir_entries = fs::read_dir(folder)?.collect::<Result<Vec<_>, _>>()?;
+    dir_entries.sort_by_key(|dir_entry| dir_entry.file_name());
+
+    for dir_entry in dir_entries {
+        let file_type = dir_entry.file_type()?;
+        if file_type.is_symlink() {
+            continue;
+        }
+        let name = dir_entry.file_name().to_string_lossy().to_string();
+        let relative_path = if relative_folder.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative_folder, name)
+        };
+        if filter.is_excluded(&relative_path) {
+            continue;
+        }
+
+        let path = dir_entry.path();
+        if file_type.is_dir() {
+            let is_empty = fs::read_dir(&path)?.next().is_none();
+            if is_empty {
+                if filter.is_included(&relative_path) && is_allowed(&path) {
+                    files.push(PackedFile {
+                        path,
+                        entry_name: format!("{}/", relative_path),
+                        is_dir: true,
+                    });
+                }
+            } else {
+                collect_files(&path, &relative_path, filter, is_allowed, skip, files)?;
+            }
+        } else if !skip.contains(&path) && filter.is_included(&relative_path) && is_allowed(&path) {
+            files.push(PackedFile {
+                path,
+                entry_name: relative_path,
+                is_dir: false,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Packs all files of the source folder into the writer, with paths relative to the source.
+/// Empty folders are added as folder entries, so they are recreated on extraction.
+/// Files and folders rejected by `is_allowed`, like paths outside the fs scope, are skipped.
+pub fn pack<A: Fn(&Path) -> bool, F: FnMut(FileProgress)>(
+    writer: &mut ZipWriterHelper,
+    source: &Path,
+    options: &PackOptions,
+    is_allowed: A,
+    cancel: &AtomicBool,
+    mut on_file_done: F,
+) -> Result<(), ZipSupportError> {
+    let filter = PackFilter::new(options)?;
+
+    // canonical paths, since the archive might be written into the packed folder
+    let source = dunce::canonicalize(source)?;
+    let mut skip = vec![dunce::canonicalize(&writer.path)?];
+    // the original of an updated archive is still read until finishing
+    if let Ok(target) = dunce::canonicalize(&writer.target) {
+        skip.push(target);
+    }
+    let mut files = Vec::new();
+    collect_files(&source, "", &filter, &is_allowed, &skip, &mut files)?;
+
+    let mut bytes_written = 0;
+    for (files_done, file) in files.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ZipSupportError::Cancelled);
+        }
+        let file_options = writer.options.to_file_options(None)?;
+        if file.is_dir {
+            writer.start_directory(&file.entry_name, file_options)?;
+        } else {
+            writer.start_entry(&file.entry_name, file_options)?;
+            let mut buf_reader = BufReader::new(File::open(&file.path)?);
+            bytes_written += io::copy(&mut buf_reader, &mut writer.writer)?;
+        }
+        on_file_done(FileProgress {
+            files_done: files_done + 1,
+            files_total: files.len(),
+            bytes_written,
+            entry: &file.entry_name,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+    fn entry_names(path: &Path) -> Vec<String> {
+        let archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let mut names: Vec<String> = archive.file_names().map(String::from).collect();
+        names.sort();
+        names
+    }
+
+    fn create_source(folder: &Path) {
+        fs::create_dir_all(folder.join("empty/nested")).unwrap();
+        fs::create_dir_all(folder.join("files")).unwrap();
+        fs::create_dir_all(folder.join("skipped")).unwrap();
+        fs::write(folder.join("files/a.txt"), "a").unwrap();
+        fs::write(folder.join("files/b.log"), "b").unwrap();
+    }
+
+    fn pack_folder(
+        source: &Path,
+        target: &Path,
+        options: PackOptions,
+        cancel: bool,
+    ) -> Result<(), ZipSupportError> {
+        pack_folder_in_scope(source, target, options, |_| true, cancel)
+    }
+
+    fn pack_folder_in_scope<A: Fn(&Path) -> bool>(
+        source: &Path,
+        target: &Path,
+        options: PackOptions,
+        is_allowed: A,
+        cancel: bool,
+    ) -> Result<(), ZipSupportError> {
+        let mut writer = ZipWriterHelper::create(target, options.writer.clone())?;
+        let result = pack(
+            &mut writer,
+            source,
+            &options,
+            is_allowed,
+            &AtomicBool::new(cancel),
+            |_| {},
+        );
+        match result {
+            Ok(()) => Ok(writer.finish()?),
+            Err(err) => {
+                writer.discard();
+                Err(err)
+            }
+        }
+    }
+
+    #[test]
+    fn packs_empty_folders_as_entries() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        create_source(&source);
+        let target = temp.path().join("packed.zip");
+        let options = PackOptions {
+            exclude: vec![String::from("skipped"), String::from("**/*.log")],
+            ..Default::default()
+        };
+        pack_folder(&source, &target, options, false).unwrap();
+        assert_eq!(entry_names(&target), vec!["empty/nested/", "files/a.txt"]);
+    }
+
+    #[test]
+    fn skips_denied_paths() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        create_source(&source);
+        fs::create_dir_all(source.join("secret/empty")).unwrap();
+        fs::write(source.join("secret/key.txt"), "key").unwrap();
+        let target = temp.path().join("packed.zip");
+
+        let is_allowed = |path: &Path| {
+            !path
+                .components()
+                .any(|component| component.as_os_str() == "secret")
+        };
+        pack_folder_in_scope(&source, &target, PackOptions::default(), is_allowed, false).unwrap();
+        assert_eq!(
+            entry_names(&target),
+            vec!["empty/nested/", "files/a.txt", "files/b.log", "skipped/"]
+        );
+    }
+
+    #[test]
+    fn cancel_reports_the_cancel_error() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        create_source(&source);
+        let target = temp.path().join("packed.zip");
+        let err = pack_folder(&source, &target, PackOptions::default(), true).unwrap_err();
+        assert!(matches!(err, ZipSupportError::Cancelled));
+        assert!(!target.exists());
+    }
+
+    fn create_original(target: &Path) {
+        let mut original = ZipWriter::new(File::create(target).unwrap());
+        original
+            .start_file("old.txt", FileOptions::default())
+            .unwrap();
+        original.write_all(b"old").unwrap();
+        original.finish().unwrap();
+    }
+
+    #[test]
+    fn failed_update_keeps_the_original_archive() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        create_source(&source);
+        let target = temp.path().join("packed.zip");
+        create_original(&target);
+
+        let mut options = PackOptions::default();
+        options.writer.update = true;
+        assert!(pack_folder(&source, &target, options, true).is_err());
+        assert_eq!(entry_names(&target), vec!["old.txt"]);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2); // no staging file left
+    }
+
+    #[test]
+    fn cancel_keeps_an_existing_archive() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("source");
+        create_source(&source);
+        let target = temp.path().join("packed.zip");
+        create_original(&target);
+        let original = fs::read(&target).unwrap();
+
+        assert!(pack_folder(&source, &target, PackOptions::default(), true).is_err());
+        assert_eq!(fs::read(&target).unwrap(), original);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2); // no staging file left
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::Read;
+
+use super::{archive::ArchiveReader, error::ZipSupportError};
+
+const MAX_SYMLINK_TARGET_LENGTH: u64 = 4 * 1024;
+
+fn unsafe_entry(code: &'static str, entry: &str) -> ZipSupportError {
+    ZipSupportError::UnsafeEntry {
+        code,
+        entry: entry.to_string(),
+    }
+}
+
+// both separators are handled, since the archives might be created on any system
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '\\'])
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let starts_with_separator = path.starts_with(['/', '\\']);
+    let starts_with_drive_letter =
+        bytes.len() > 1 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    starts_with_separator || starts_with_drive_letter
+}
+
+/// Checks that the entry name stays inside the folder it is extracted to.
+/// Colons are also rejected, since they are drive letters or alternate data streams on windows.
+pub fn validate_entry_name(name: &str) -> Result<(), ZipSupportError> {
+    if name.is_empty() || name.contains('\0') {
+        return Err(unsafe_entry("zip.entry.path.invalid", name));
+    }
+    if is_absolute_path(name) {
+        return Err(unsafe_entry("zip.entry.path.absolute", name));
+    }
+    if name.contains(':') {
+        return Err(unsafe_entry("zip.entry.path.invalid", name));
+    }
+    if split_path(name).any(|component| component == "..") {
+        return Err(unsafe_entry("zip.entry.path.traversal", name));
+    }
+    Ok(())
+}
+
+// the target is relative to the folder of the link
+fn symlink_target_stays_inside(name: &str, target: &str) -> bool {
+    if target.is_empty() || is_absolute_path(target) {
+        return false;
+    }
+    let mut depth: usize = split_path(name)
+        .filter(|component| !component.is_empty() && *component != ".")
+        .count()
+        .saturating_sub(1);
+    for component in split_path(target) {
+        match component {
+            "" | "." => (),
+            ".." => match depth.checked_sub(1) {
+                Some(new_depth) => depth = new_depth,
+                None => return false,
+            },
+            _ => depth += 1,
+        }
+    }
+    true
+}
+
+fn validate_symlink(name: &str, reader: &mut dyn Read) -> Result<(), ZipSupportError> {
+    let mut target = String::new();
+    reader
+        .read_to_string(&mut target)
+        .map_err(|_| unsafe_entry("zip.entry.symlink.outside", name))?;
+    if symlink_target_stays_inside(name, &target) {
+        Ok(())
+    } else {
+        Err(unsafe_entry("zip.entry.symlink.outside", name))
+    }
+}
+
+/// Validates every entry of the archive, before anything is written.
+/// Only symlinks are read, to check their target.
+pub fn validate_archive(archive: &mut ArchiveReader) -> Result<(), ZipSupportError> {
+    let mut symlink_indices = Vec::new();
+    for (index, entry) in archive.entries().iter().enumerate() {
+        validate_entry_name(&entry.name)?;
+        if entry.is_symlink {
+            if entry.size > MAX_SYMLINK_TARGET_LENGTH {
+                return Err(unsafe_entry("zip.entry.symlink.outside", &entry.name));
+            }
+            symlink_indices.push(index);
+        }
+    }
+    if symlink_indices.is_empty() {
+        return Ok(());
+    }
+    archive.read_entries(&symlink_indices, |_, entry, reader| {
+        validate_symlink(&entry.name, reader)
+    })
+}
@@ -0,0 +1,222 @@
+use crc32fast::Hasher;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek},
+};
+use zip::{result::ZipError, ZipArchive};
+
+use super::{
+    archive,
+    error::ZipSupportError,
+    limits::{self, LimitedReader, ZipLimits},
+    safety,
+};
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A single problem found in an archive.
+#[derive(Serialize)]
+pub struct ZipProblem {
+    pub code: &'static str,
+    pub entry: Option<String>,
+    pub message: String,
+}
+
+impl ZipProblem {
+    fn from_error(error: ZipSupportError, entry: Option<&str>) -> ZipProblem {
+        let code = match &error {
+            ZipSupportError::Zip(ZipError::UnsupportedArchive(_)) => "zip.verify.entry.unsupported",
+            ZipSupportError::Zip(_) => "zip.verify.entry.corrupt",
+            _ => error.code(),
+        };
+        ZipProblem {
+            code,
+            entry: error.entry().or(entry).map(String::from),
+            message: error.to_string(),
+        }
+    }
+}
+
+// entries that only differ in case would overwrite each other on windows
+fn find_name_collisions(names: &[String], problems: &mut Vec<ZipProblem>) {
+    let mut seen_names: HashMap<&str, usize> = HashMap::new();
+    let mut seen_lowercase_names: HashMap<String, &str> = HashMap::new();
+    for name in names {
+        let count = seen_names.entry(name).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            problems.push(ZipProblem {
+                code: "zip.verify.entry.duplicate",
+                entry: Some(name.clone()),
+                message: format!("The entry '{}' exists multiple times.", name),
+            });
+        }
+        if *count > 1 || name.ends_with('/') {
+            continue; // folders are merged anyway
+        }
+        match seen_lowercase_names.get(&name.to_lowercase()) {
+            Some(other_name) => problems.push(ZipProblem {
+                code: "zip.verify.entry.case.collision",
+                entry: Some(name.clone()),
+                message: format!(
+                    "The entry '{}' only differs in case from '{}'.",
+                    name, other_name
+                ),
+            }),
+            None => {
+                seen_lowercase_names.insert(name.to_lowercase(), name);
+            }
+        }
+    }
+}
+
+// returns false if the content does not match the checksum of the entry
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+) -> Result<bool, ZipSupportError> {
+    let file = archive.by_index(index)?;
+    let expected_crc32 = file.crc32();
+    let declared_size = file.size();
+    let name = file.name().to_string();
+    let mut reader = LimitedReader::new(file, declared_size, &name);
+
+    let mut hasher = Hasher::new();
+    let mut bytes_read: u64 = 0;
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    let read_result = loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read_bytes) => {
+                hasher.update(&buffer[..read_bytes]);
+                bytes_read += read_bytes as u64;
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => break Err(error),
+        }
+    };
+    // the zip reader also fails on a wrong checksum, but only after all bytes were returned,
+    // so the own checksum decides if the whole content was read
+    if bytes_read == declared_size && hasher.finalize() != expected_crc32 {
+        return Ok(false);
+    }
+    read_result?;
+    Ok(true)
+}
+
+/// Reads every entry of the archive and collects all found problems, instead of stopping at the first.
+pub fn verify<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    archive_size: u64,
+) -> Result<Vec<ZipProblem>, ZipSupportError> {
+    // nothing is decompressed if the archive might be a zip bomb
+    let entries = archive::list_zip_entries(archive)?;
+    if let Err(error) = limits::check_entries(&entries, archive_size, &ZipLimits::default()) {
+        return Ok(vec![ZipProblem::from_error(error, None)]);
+    }
+
+    let mut problems = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        if let Err(error) = safety::validate_entry_name(&entry.name) {
+            problems.push(ZipProblem::from_error(error, Some(&entry.name)));
+        }
+        match read_entry(archive, index) {
+            Ok(true) => (),
+            Ok(false) => problems.push(ZipProblem {
+                code: "zip.verify.entry.crc",
+                entry: Some(entry.name.clone()),
+                message: format!(
+                    "The content of the entry '{}' does not match its checksum.",
+                    entry.name
+                ),
+            }),
+            Err(error) => problems.push(ZipProblem::from_error(error, Some(&entry.name))),
+        }
+    }
+    let names: Vec<String> = entries.into_iter().map(|entry| entry.name).collect();
+    find_name_collisions(&names, &mut problems);
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    fn create_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        // stored, so the content can be found and changed in the archive
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, content) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn verify_bytes(bytes: Vec<u8>) -> Vec<(&'static str, Option<String>)> {
+        let size = bytes.len() as u64;
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        verify(&mut archive, size)
+            .unwrap()
+            .into_iter()
+            .map(|problem| (problem.code, problem.entry))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_archives() {
+        let bytes = create_archive(&[("a.txt", b"content a"), ("folder/b.txt", b"content b")]);
+        assert!(verify_bytes(bytes).is_empty());
+    }
+
+    #[test]
+    fn reports_corrupted_entries() {
+        let mut bytes = create_archive(&[("a.txt", b"unchanged"), ("b.txt", b"original content")]);
+        let position = bytes
+            .windows(8)
+            .position(|window| window == b"original")
+            .unwrap();
+        bytes[position..(position + 8)].copy_from_slice(b"modified");
+        assert_eq!(
+            verify_bytes(bytes),
+            vec![("zip.verify.entry.crc", Some(String::from("b.txt")))]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_names() {
+        let mut names = vec![String::from("a.txt"), String::from("b.txt")];
+        names.push(String::from("a.txt"));
+        names.push(String::from("a.txt"));
+        let mut problems = Vec::new();
+        find_name_collisions(&names, &mut problems);
+        let codes: Vec<(&str, Option<String>)> = problems
+            .into_iter()
+            .map(|problem| (problem.code, problem.entry))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![("zip.verify.entry.duplicate", Some(String::from("a.txt")))]
+        );
+    }
+
+    #[test]
+    fn reports_case_collisions() {
+        let bytes = create_archive(&[
+            ("Data/a.txt", b""),
+            ("data/A.txt", b""),
+            ("folder/", b""),
+            ("FOLDER/", b""),
+        ]);
+        assert_eq!(
+            verify_bytes(bytes),
+            vec![(
+                "zip.verify.entry.case.collision",
+                Some(String::from("data/A.txt"))
+            )]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use zip::{write::FileOptions, CompressionMethod, DateTime};
+
+use super::error::ZipSupportError;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ZipCompression {
+    Stored,
+    Deflated,
+}
+
+/// Options of a written entry. Unset values fall back to the options of the writer.
+#[derive(Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZipEntryOptions {
+    pub compression: Option<ZipCompression>,
+
+    /// Only valid for deflate, 0 - 9.
+    pub compression_level: Option<i32>,
+
+    /// Only the permission bits are used, like 0o644.
+    pub unix_permissions: Option<u32>,
+
+    /// Local time without zone, like "2024-01-31T12:00:00".
+    pub last_modified: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZipWriterOptions {
+    /// Defaults for every entry of the archive.
+    #[serde(flatten)]
+    pub entry_defaults: ZipEntryOptions,
+
+    /// The same content always results in the same archive.
+    /// Timestamps are fixed and the entries are sorted by name when the writer is closed.
+    pub reproducible: bool,
+
+    /// Keeps the entries of an existing archive, without recompressing them.
+    /// Written entries replace kept entries with the same name.
+    /// The archive is only replaced when the writer is closed.
+    pub update: bool,
+}
+
+// same format as the metadata of the reader
+fn parse_last_modified(last_modified: &str) -> Result<DateTime, ZipSupportError> {
+    let parts: Vec<&str> = last_modified.split(['-', 'T', ':']).collect();
+    let date_time = match parts[..] {
+        [year, month, day, hour, minute, second] => (|| {
+            DateTime::from_date_and_time(
+                year.parse().ok()?,
+                month.parse().ok()?,
+                day.parse().ok()?,
+                hour.parse().ok()?,
+                minute.parse().ok()?,
+                second.parse().ok()?,
+            )
+            .ok()
+        })(),
+        _ => None,
+    };
+    date_time.ok_or(ZipSupportError::Key(
+        "zip.writer.options.last.modified.invalid",
+    ))
+}
+
+impl ZipWriterOptions {
+    /// Combines the writer options with the options of the entry.
+    pub fn to_file_options(
+        &self,
+        entry: Option<&ZipEntryOptions>,
+    ) -> Result<FileOptions, ZipSupportError> {
+        let defaults = &self.entry_defaults;
+        let compression = entry
+            .and_then(|entry| entry.compression)
+            .or(defaults.compression);
+        let compression_level = entry
+            .and_then(|entry| entry.compression_level)
+            .or(defaults.compression_level);
+        let unix_permissions = entry
+            .and_then(|entry| entry.unix_permissions)
+            .or(defaults.unix_permissions);
+        let last_modified = entry
+            .and_then(|entry| entry.last_modified.as_deref())
+            .or(defaults.last_modified.as_deref());
+
+        let mut file_options = FileOptions::default().compression_level(compression_level);
+        if let Some(compression) = compression {
+            file_options = file_options.compression_method(match compression {
+                ZipCompression::Stored => CompressionMethod::Stored,
+                ZipCompression::Deflated => CompressionMethod::Deflated,
+            });
+        }
+        if let Some(unix_permissions) = unix_permissions {
+            file_options = file_options.unix_permissions(unix_permissions);
+        }
+        match last_modified {
+            Some(last_modified) => {
+                file_options = file_options.last_modified_time(parse_last_modified(last_modified)?)
+            }
+            // the earliest time zip supports, instead of the current time
+            None if self.reproducible => {
+                file_options = file_options.last_modified_time(DateTime::default())
+            }
+            None => (),
+        }
+        Ok(file_options)
+    }
+}
@@ -20,7 +20,7 @@ export async function installExtensionsButtonCallback(
   let path = location;
   if (location === undefined) {
     const result = await openFileDialog(gameFolder, [
-      { name: 'Zip files', extensions: ['zip'] },
+      { name: 'Archive files', extensions: ['zip', '7z', 'gz', 'tgz'] },
     ]);
     if (!result.isPresent()) {
       return false;
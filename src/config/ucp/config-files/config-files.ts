@@ -1,6 +1,6 @@
 /* eslint-disable no-param-reassign */
 import { stringify as yamlStringify } from 'yaml';
-import { writeTextFile, loadYaml } from '../../../tauri/tauri-files';
+import { writeTextFileAtomic, loadYaml } from '../../../tauri/tauri-files';
 import Result from '../../../util/structs/result';
 import { ConfigurationQualifier } from '../../../function/configuration/state';
 import Logger from '../../../util/scripts/logging';
@@ -246,7 +246,7 @@ export async function saveUCPConfig(
   filePath: string,
   configurationQualifier: { [key: string]: ConfigurationQualifier },
 ) {
-  const result = await writeTextFile(
+  const result = await writeTextFileAtomic(
     filePath,
     toYaml(
       serializeUCPConfig(
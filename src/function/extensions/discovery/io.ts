@@ -46,27 +46,26 @@ export async function readLocales(
 
   const locFolder = await eh.doesEntryExist(`${LOCALE_FOLDER}/`);
   if (locFolder) {
-    // eslint-disable-next-line no-restricted-syntax
-    for (const language of locales) {
-      // eslint-disable-next-line no-await-in-loop
-      if (await eh.doesEntryExist(`${LOCALE_FOLDER}/${language}.yml`)) {
-        const translation = yaml.parse(
-          // eslint-disable-next-line no-await-in-loop
-          await eh.getTextContents(`${LOCALE_FOLDER}/${language}.yml`),
-        ) as Translation;
+    await Promise.all(
+      locales.map(async (language) => {
+        if (await eh.doesEntryExist(`${LOCALE_FOLDER}/${language}.yml`)) {
+          const translation = yaml.parse(
+            await eh.getTextContents(`${LOCALE_FOLDER}/${language}.yml`),
+          ) as Translation;
 
-        translations[language] = Object.fromEntries(
-          Object.entries(translation).map(([key, value]) => [
-            key.toLowerCase(),
-            value.replaceAll('&', ''),
-          ]),
-        );
-      } else {
-        LOGGER.msg(
-          `No locale file found for: ${name}: ${LOCALE_FOLDER}/${language}.yml`,
-        ).info();
-      }
-    }
+          translations[language] = Object.fromEntries(
+            Object.entries(translation).map(([key, value]) => [
+              key.toLowerCase(),
+              value.replaceAll('&', ''),
+            ]),
+          );
+        } else {
+          LOGGER.msg(
+            `No locale file found for: ${name}: ${LOCALE_FOLDER}/${language}.yml`,
+          ).info();
+        }
+      }),
+    );
   }
 
   return translations;
@@ -4,30 +4,27 @@ import { basename } from '@tauri-apps/api/path';
 import RustZipExtensionHandle from '../handles/rust-zip-extension-handle';
 import { Definition } from '../../../config/ucp/common';
 import { extractZipToPath } from '../../../tauri/tauri-invoke';
-import {
-  copyFile,
-  readDir,
-  removeDir,
-  renameFile,
-  writeTextFile,
-} from '../../../tauri/tauri-files';
+import { copyFile, writeTextFile } from '../../../tauri/tauri-files';
 import Logger from '../../../util/scripts/logging';
 import {
-  UCP_CACHE_FOLDER,
   UCP_MODULES_FOLDER,
   UCP_PLUGINS_FOLDER,
 } from '../../global/constants/file-constants';
 
 const LOGGER = new Logger(`install-extensions.ts`);
 
+// the backend detects the format by the content, the extension is only used for names
+const ARCHIVE_EXTENSIONS = ['.zip', '.7z', '.gz', '.tgz'];
+
+const getArchiveExtension = (path: string) =>
+  ARCHIVE_EXTENSIONS.find((extension) => path.endsWith(extension));
+
 export type InstallPluginOptions = {
   zapRootFolder?: boolean;
-  cacheDir?: string;
 };
 
 const InstallPluginDefaults = {
   zapRootFolder: false,
-  cacheDir: `${UCP_CACHE_FOLDER}`,
 } as InstallPluginOptions;
 
 export const installPlugin = async (
@@ -37,64 +34,20 @@ export const installPlugin = async (
 ) => {
   const options = { ...InstallPluginDefaults, ...opts };
   const fileName = await basename(path);
-  const folderName = fileName.slice(undefined, -4);
+  const folderName = fileName.slice(
+    undefined,
+    -(getArchiveExtension(fileName) ?? '.zip').length,
+  );
   const destination = `${gameFolder}/${UCP_PLUGINS_FOLDER}${folderName}`;
 
   if (await exists(destination)) {
     throw Error(`plugin already exists: ${folderName}`);
   }
 
-  if (options.zapRootFolder) {
-    const tempFolder = `${gameFolder}/${options.cacheDir}${folderName}`;
-    if (await exists(tempFolder)) {
-      throw Error(`temp folder already exists: ${tempFolder}`);
-    }
-    LOGGER.msg(`Extracting zip ${path} to temp folder: ${tempFolder}`).debug();
-    await extractZipToPath(path, tempFolder);
-
-    const readDirResult = await readDir(tempFolder, {
-      recursive: false,
-    });
-
-    if (!readDirResult.isOk()) {
-      throw Error(
-        `Failed to read dir: ${tempFolder}. Reason: ${readDirResult.err().get()}`,
-      );
-    }
-
-    const entries = readDirResult
-      .getOrThrow()
-      .filter((fe) => fe.children !== null);
-
-    if (entries.length !== 1) {
-      throw Error(
-        `Unexpected number of directories in directory, expected one: ${tempFolder}`,
-      );
-    }
-
-    const subFolder = entries.at(0)!.path;
-
-    LOGGER.msg(
-      `Moving folder ${subFolder} to new location: ${destination}`,
-    ).debug();
-    const renameResult = await renameFile(subFolder, destination);
-
-    if (!renameResult.isOk()) {
-      throw Error(
-        `Failed to move plugin folder ${subFolder} to new location: ${renameResult.err().get()}`,
-      );
-    }
-
-    LOGGER.msg(`Removing temp folder: ${tempFolder}`).debug();
-    const removeResult = await removeDir(tempFolder);
-    if (!removeResult.isOk()) {
-      throw Error(
-        `Failed to remove temp folder ${tempFolder}. Reason: ${removeResult.err().get()}`,
-      );
-    }
-  } else {
-    await extractZipToPath(path, destination);
-  }
+  LOGGER.msg(`Extracting archive ${path} to: ${destination}`).debug();
+  await extractZipToPath(path, destination, undefined, {
+    stripRootFolder: options.zapRootFolder,
+  });
 };
 
 export const installModule = async (
@@ -125,8 +78,11 @@ export const installModule = async (
 
 // eslint-disable-next-line import/prefer-default-export
 export const installExtension = async (gameFolder: string, path: string) => {
-  if (!path.endsWith('.zip')) {
-    throw Error(`Path does not end with '.zip': ${path}`);
+  const archiveExtension = getArchiveExtension(path);
+  if (archiveExtension === undefined) {
+    throw Error(
+      `Path does not end with one of '${ARCHIVE_EXTENSIONS.join("', '")}': ${path}`,
+    );
   }
 
   let definition = {
@@ -135,7 +91,7 @@ export const installExtension = async (gameFolder: string, path: string) => {
 
   await RustZipExtensionHandle.with(path, async (eh) => {
     if (!(await eh.doesEntryExist('definition.yml'))) {
-      throw Error(`Zip file does not contain definition.yml`);
+      throw Error(`Archive does not contain definition.yml`);
     }
 
     definition = yaml.parse(
@@ -148,6 +104,10 @@ export const installExtension = async (gameFolder: string, path: string) => {
   }
 
   if (definition.type === 'module') {
+    // modules are loaded by the game as zip files
+    if (archiveExtension !== '.zip') {
+      throw Error(`Modules can only be installed from zip files: ${path}`);
+    }
     return installModule(gameFolder, path);
   }
 
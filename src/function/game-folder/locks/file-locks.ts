@@ -1,6 +1,7 @@
 import { getStore } from '../../../hooks/jotai/base';
-import { onFsExists, renameFile } from '../../../tauri/tauri-files';
+import { getRunningGameProcesses } from '../../../tauri/tauri-invoke';
 import Logger from '../../../util/scripts/logging';
+import Result from '../../../util/structs/result';
 import { GAME_FOLDER_ATOM } from '../interface';
 
 const LOGGER = new Logger(`file-locks.ts`);
@@ -9,44 +10,25 @@ const LOGGER = new Logger(`file-locks.ts`);
 export const hintThatGameMayBeRunning = async () => {
   const folder = getStore().get(GAME_FOLDER_ATOM);
 
-  const path = `${folder}/binkw32.dll`;
+  const processesResult = await Result.tryAsync(
+    getRunningGameProcesses,
+    folder,
+  );
 
-  if (!(await onFsExists(path))) {
-    throw Error(
-      `binkw32.dll does not exist, are we in a game folder? path: ${path}`,
-    );
-  }
-
-  const newPath = `${path}.tmp`;
-
-  let result = false;
-  let moved = false;
-
-  try {
-    const renameResult = await renameFile(path, newPath);
-
-    if (renameResult.isErr()) {
-      LOGGER.msg(
-        `Renaming binkw32.dll failed, because: ${renameResult.err().getOrElse('')}`,
-      ).error();
-      result = true;
-    }
-
-    if (renameResult.isOk()) {
-      result = false;
-      moved = true;
-    }
-  } catch (e: unknown) {
+  if (processesResult.isErr()) {
     LOGGER.msg(
-      `Renaming binkw32.dll failed for an unknown reason: ${e}`,
+      `Checking for running game processes failed, because: ${processesResult.err().getOrElse('')}`,
     ).error();
-
-    result = true;
+    // better safe than sorry
+    return true;
   }
 
-  if (moved) {
-    await renameFile(newPath, path);
-  }
+  const processes = processesResult.ok().getOrElse([]);
+  processes.forEach((process) =>
+    LOGGER.msg(
+      `Game is running with pid ${process.pid}, started at ${new Date(process.start_time * 1000).toISOString()}${process.wine ? ' through wine' : ''}: ${process.path ?? process.name}`,
+    ).info(),
+  );
 
-  return result;
+  return processes.length > 0;
 };
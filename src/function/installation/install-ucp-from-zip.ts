@@ -8,6 +8,7 @@ import { activateUCP } from '../ucp-files/activate-ucp';
 import { getStore } from '../../hooks/jotai/base';
 import { initializeUCPVersion } from '../ucp-files/ucp-version';
 import { MessageType } from '../../localization/localization';
+import { UCP_INSTALL_MANIFEST } from '../global/constants/file-constants';
 
 // eslint-disable-next-line import/prefer-default-export
 export async function installUCPFromZip(
@@ -19,7 +20,12 @@ export async function installUCPFromZip(
     (await createRealBink()).throwIfErr();
 
     createStatusToast(ToastType.INFO, 'zip.extract');
-    await extractZipToPath(zipFilePath, gameFolder);
+    // transactional, to never leave a half upgraded game folder
+    // the manifest allows to remove the installed files again
+    await extractZipToPath(zipFilePath, gameFolder, undefined, {
+      transactional: true,
+      manifest: `${gameFolder}/${UCP_INSTALL_MANIFEST}`,
+    });
 
     // Force a refresh on this atom to ensure activateUCP() is dealing with the right IO state
     await initializeUCPVersion(gameFolder);
@@ -36,10 +36,23 @@ import { convertFileSrc } from '@tauri-apps/api/tauri';
 import Result from '../util/structs/result';
 import {
   readAndFilterPaths as invokeReadAndFilterPaths,
+  readAndFilterDirEntries as invokeReadAndFilterDirEntries,
+  ReadDirOptions,
   slashify as invokeSlashify,
   canonicalize as invokeCanonicalize,
   scanFileForBytes as invokeScanFileForBytes,
+  scanFileForPatterns as invokeScanFileForPatterns,
+  getPeInfo as invokeGetPeInfo,
+  PeInfo,
+  writeFileAtomic as invokeWriteFileAtomic,
+  writeTextFileAtomic as invokeWriteTextFileAtomic,
+  replaceFileSafely as invokeReplaceFileSafely,
+  restoreFileBackup as invokeRestoreFileBackup,
+  watchFolder as invokeWatchFolder,
+  unwatchFolder as invokeUnwatchFolder,
+  FsWatchOptions,
 } from './tauri-invoke';
+import { FsChange, onFsWatch } from './tauri-listen';
 import Option from '../util/structs/option';
 
 // WARNING: Tauri funcs lie about their return.
@@ -244,6 +257,59 @@ export async function scanFileForBytes(
   return (await result).mapOk(Option.ofNullable);
 }
 
+export async function scanFileForPatterns(
+  path: string,
+  patterns: string[],
+  maxMatches?: number,
+  scanAmount?: number,
+): Promise<Result<number[][], Error>> {
+  return Result.tryAsync(
+    invokeScanFileForPatterns,
+    path,
+    patterns,
+    maxMatches,
+    scanAmount,
+  );
+}
+
+export async function getPeInfo(path: string): Promise<Result<PeInfo, Error>> {
+  return Result.tryAsync(invokeGetPeInfo, path);
+}
+
+// errors are SafeWriteErrors with a code, like "file.locked"
+export async function writeFileAtomic(
+  path: string,
+  content: BinaryFileContents | string,
+): Promise<Result<void, Error>> {
+  return Result.tryAsync(invokeWriteFileAtomic, path, content);
+}
+
+export async function writeTextFileAtomic(
+  path: string,
+  content: string,
+): Promise<Result<void, Error>> {
+  return Result.tryAsync(invokeWriteTextFileAtomic, path, content);
+}
+
+export async function replaceFileSafely(
+  target: string,
+  replacement: string,
+  keepBackup?: boolean,
+): Promise<Result<string | null, Error>> {
+  return Result.tryAsync(
+    invokeReplaceFileSafely,
+    target,
+    replacement,
+    keepBackup,
+  );
+}
+
+export async function restoreFileBackup(
+  path: string,
+): Promise<Result<void, Error>> {
+  return Result.tryAsync(invokeRestoreFileBackup, path);
+}
+
 // GET FOLDER
 
 export async function readDir(dir: string, options?: FsDirOptions | undefined) {
@@ -259,8 +325,57 @@ export async function removeDir(
   return ignoreNotFound ? validateRemoval(path, result) : result;
 }
 
-export async function readAndFilterPaths(dir: string, pattern: string) {
-  return Result.tryAsync(invokeReadAndFilterPaths, dir, pattern);
+export async function readAndFilterPaths(
+  dir: string,
+  pattern: string,
+  options?: ReadDirOptions,
+) {
+  return Result.tryAsync(invokeReadAndFilterPaths, dir, pattern, options);
+}
+
+export async function readAndFilterDirEntries(
+  dir: string,
+  pattern: string,
+  options?: ReadDirOptions,
+) {
+  return Result.tryAsync(invokeReadAndFilterDirEntries, dir, pattern, options);
+}
+
+// calls the callback with the debounced changes of the folder
+// returns a function to stop watching, watches are also stopped if the page reloads
+export async function watchFolder(
+  path: string,
+  callback: (changes: FsChange[]) => void,
+  options?: FsWatchOptions,
+): Promise<Result<() => Promise<void>, Error>> {
+  return Result.tryAsync(async () => {
+    let watchId: number | undefined;
+    // the first changes might arrive before the id, so they are kept until it is known
+    let earlyEvents: { id: number; changes: FsChange[] }[] | undefined = [];
+    const unlisten = await onFsWatch((event) => {
+      if (earlyEvents) {
+        earlyEvents.push(event.payload);
+      } else if (event.payload.id === watchId) {
+        callback(event.payload.changes);
+      }
+    });
+    try {
+      watchId = await invokeWatchFolder(path, options);
+    } catch (err) {
+      unlisten();
+      throw err;
+    }
+    const id = watchId;
+    const events = earlyEvents;
+    earlyEvents = undefined;
+    events
+      .filter((payload) => payload.id === id)
+      .forEach((payload) => callback(payload.changes));
+    return async () => {
+      unlisten();
+      await invokeUnwatchFolder(id);
+    };
+  });
 }
 
 export async function getDownloadFolder() {
@@ -6,6 +6,7 @@ import { invoke } from '@tauri-apps/api/tauri';
 const PLUGIN_CONFIG = 'tauri-plugin-ucp-config';
 const PLUGIN_LOGGING = 'tauri-plugin-ucp-logging';
 const PLUGIN_ZIP = 'tauri-plugin-ucp-zip-support';
+const PLUGIN_FS_WATCHER = 'tauri-plugin-ucp-fs-watcher';
 
 const TEXT_ENCODER = new TextEncoder();
 
@@ -35,28 +36,54 @@ const TAURI_COMMAND = {
   CONFIG_SAVE: buildPluginCmd(PLUGIN_CONFIG, 'save_config'),
 
   ZIP_EXTRACT_TO_PATH: buildPluginCmd(PLUGIN_ZIP, 'extract_zip_to_path'),
+  ZIP_CANCEL_EXTRACTION: buildPluginCmd(PLUGIN_ZIP, 'cancel_zip_extraction'),
+  ZIP_UNINSTALL_EXTRACTION: buildPluginCmd(PLUGIN_ZIP, 'uninstall_zip_extraction'),
+  ZIP_PACK_DIRECTORY: buildPluginCmd(PLUGIN_ZIP, 'pack_directory_to_zip'),
+  ZIP_VERIFY: buildPluginCmd(PLUGIN_ZIP, 'verify_zip'),
   ZIP_READER_LOAD: buildPluginCmd(PLUGIN_ZIP, 'load_zip_reader'),
+  ZIP_READER_LOAD_FROM_ENTRY: buildPluginCmd(PLUGIN_ZIP, 'load_zip_reader_from_entry'),
+  ZIP_READER_LOAD_FROM_BINARY: buildPluginCmd(PLUGIN_ZIP, 'load_zip_reader_from_binary'),
   ZIP_READER_CLOSE: buildPluginCmd(PLUGIN_ZIP, 'close_zip_reader'),
   ZIP_READER_IS_EMPTY: buildPluginCmd(PLUGIN_ZIP, 'is_zip_reader_empty'),
   ZIP_READER_GET_NUMBER_OF_ENTRIES: buildPluginCmd(PLUGIN_ZIP, 'get_zip_reader_number_of_entries'),
   ZIP_READER_EXIST_ENTRY: buildPluginCmd(PLUGIN_ZIP, 'exist_zip_reader_entry'),
   ZIP_READER_GET_ENTRY_NAMES: buildPluginCmd(PLUGIN_ZIP, 'get_zip_reader_entry_names'),
+  ZIP_READER_GET_ENTRY_METADATA: buildPluginCmd(PLUGIN_ZIP, 'get_zip_reader_entry_metadata'),
   ZIP_READER_GET_ENTRY_AS_BINARY: buildPluginCmd(PLUGIN_ZIP, 'get_zip_reader_entry_as_binary'),
   ZIP_READER_GET_ENTRY_AS_TEXT: buildPluginCmd(PLUGIN_ZIP, 'get_zip_reader_entry_as_text'),
+  ZIP_READER_OPEN_ENTRY_STREAM: buildPluginCmd(PLUGIN_ZIP, 'open_zip_reader_entry_stream'),
+  ZIP_READER_READ_ENTRY_STREAM: buildPluginCmd(PLUGIN_ZIP, 'read_zip_reader_entry_stream'),
+  ZIP_READER_CLOSE_ENTRY_STREAM: buildPluginCmd(PLUGIN_ZIP, 'close_zip_reader_entry_stream'),
+  ZIP_READER_EXTRACT_ENTRY_TO_FILE: buildPluginCmd(PLUGIN_ZIP, 'extract_zip_reader_entry_to_file'),
+  ZIP_READER_DIFF: buildPluginCmd(PLUGIN_ZIP, 'diff_zip_reader'),
   ZIP_WRITER_LOAD: buildPluginCmd(PLUGIN_ZIP, 'load_zip_writer'),
   ZIP_WRITER_CLOSE: buildPluginCmd(PLUGIN_ZIP, 'close_zip_writer'),
   ZIP_WRITER_ADD_DIRECTORY: buildPluginCmd(PLUGIN_ZIP, 'add_zip_writer_directory',),
+  ZIP_WRITER_DELETE_ENTRY: buildPluginCmd(PLUGIN_ZIP, 'delete_zip_writer_entry'),
   ZIP_WRITER_WRITE_ENTRY_FROM_BINARY: buildPluginCmd(PLUGIN_ZIP, 'write_zip_writer_entry_from_binary'),
   ZIP_WRITER_WRITE_ENTRY_FROM_TEXT: buildPluginCmd(PLUGIN_ZIP, 'write_zip_writer_entry_from_text'),
   ZIP_WRITER_WRITE_ENTRY_FROM_FILE: buildPluginCmd(PLUGIN_ZIP, 'write_zip_writer_entry_from_file'),
 
+  FS_WATCHER_WATCH_FOLDER: buildPluginCmd(PLUGIN_FS_WATCHER, 'watch_folder'),
+  FS_WATCHER_UNWATCH_FOLDER: buildPluginCmd(PLUGIN_FS_WATCHER, 'unwatch_folder'),
+
   HASH_GET_SHA256_OF_FILE: 'get_sha256_of_file',
   OS_OPEN_PROGRAM: 'os_open_program',
+  OS_GET_RUNNING_GAME_PROCESSES: 'get_running_game_processes',
 
   FILES_SLASHIFY: 'slashify',
   FILES_CANONICALIZE: 'canonicalize',
   FILES_READ_AND_FILTER_DIR: 'read_and_filter_dir',
+  FILES_READ_AND_FILTER_DIR_ENTRIES: 'read_and_filter_dir_entries',
   FILES_SCAN_FILE_FOR_BYTES: 'scan_file_for_bytes',
+  FILES_SCAN_FILE_FOR_PATTERNS: 'scan_file_for_patterns',
+  FILES_GET_PE_INFO: 'get_pe_info',
+  FILES_WRITE_FILE_ATOMIC: 'write_file_atomic',
+  FILES_WRITE_TEXT_FILE_ATOMIC: 'write_text_file_atomic',
+  FILES_REPLACE_FILE_SAFELY: 'replace_file_safely',
+  FILES_RESTORE_FILE_BACKUP: 'restore_file_backup',
+
+  EXTENSIONS_GET_INFOS: 'get_extension_infos',
 
   LOGGING_LOG: buildPluginCmd(PLUGIN_LOGGING, 'log'),
 };
@@ -96,16 +123,182 @@ export async function saveGuiConfig(): Promise<void> {
   return invoke(TAURI_COMMAND.CONFIG_SAVE);
 }
 
+// thrown by zip commands that check the archive content,
+// the code is usable as localization key
+export class ZipSupportError extends Error {
+  code: string;
+
+  entry: string | null;
+
+  constructor(code: string, message: string, entry: string | null) {
+    super(message);
+    this.name = 'ZipSupportError';
+    this.code = code;
+    this.entry = entry;
+  }
+}
+
+async function invokeWithZipSupportError<T>(
+  cmd: string,
+  args: Record<string, unknown>,
+): Promise<T> {
+  try {
+    return await invoke<T>(cmd, args);
+  } catch (err) {
+    // tauri itself might still fail with plain strings
+    if (typeof err !== 'object' || err === null) {
+      throw err;
+    }
+    const { code, message, entry } = err as {
+      code: string;
+      message: string;
+      entry: string | null;
+    };
+    throw new ZipSupportError(code, message, entry);
+  }
+}
+
+// violations fail with "zip.limit.total.size", "zip.limit.entries", "zip.limit.ratio" or "zip.limit.entry.size"
+export interface ZipLimits {
+  maxTotalBytes?: number;
+  maxEntries?: number;
+  // only checked for entries above 1MB
+  maxRatio?: number;
+  maxEntryBytes?: number;
+}
+
+export interface ZipExtractionOptions {
+  // extract into a staging folder and swap the files in afterwards,
+  // otherwise files are written directly, in both cases the previous state is restored on errors
+  transactional?: boolean;
+  // removes the single folder containing all entries, like in GitHub zips
+  stripRootFolder?: boolean;
+  // only extracts the content of this archive folder
+  subtree?: string;
+  // only extracts entries matching the glob, checked against the destination path
+  pattern?: string;
+  // overwrites single default limits
+  limits?: ZipLimits;
+  // records created and overwritten files in a manifest at this path, to uninstall them later
+  // overwritten originals are kept next to it, an existing manifest is extended
+  manifest?: string;
+}
+
+// if a job id is given, progress is reported and the extraction can be cancelled with it
 export async function extractZipToPath(
   source: string,
   dest: string,
+  jobId?: string,
+  options?: ZipExtractionOptions,
 ): Promise<void> {
-  return invoke(TAURI_COMMAND.ZIP_EXTRACT_TO_PATH, { source, dest });
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_EXTRACT_TO_PATH, {
+    source,
+    dest,
+    jobId,
+    options,
+  });
+}
+
+// created files are removed again, overwritten files are restored
+// and the extraction fails with "zip.extract.cancelled"
+export async function cancelZipExtraction(jobId: string): Promise<void> {
+  return invoke(TAURI_COMMAND.ZIP_CANCEL_EXTRACTION, { jobId });
+}
+
+export interface ZipProblem {
+  // like "zip.verify.entry.crc", "zip.verify.entry.duplicate" or "zip.verify.entry.case.collision"
+  code: string;
+  entry: string | null;
+  message: string;
+}
+
+export interface ZipUninstallOptions {
+  // also removes or restores files changed after the extraction
+  force?: boolean;
+}
+
+export interface ZipUninstallResult {
+  removed: string[];
+  restored: string[];
+  missing: string[];
+  // changed after the extraction and kept, the manifest only contains them afterwards
+  modified: string[];
+  // could not be removed or restored, like locked files, they stay in the manifest for another try
+  failed: string[];
+}
+
+// removes the files recorded in an extraction manifest and restores the files they replaced
+export async function uninstallZipExtraction(
+  manifest: string,
+  options?: ZipUninstallOptions,
+): Promise<ZipUninstallResult> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_UNINSTALL_EXTRACTION, {
+    manifest,
+    options,
+  });
+}
+
+// reads the whole archive, an empty list means no problems were found
+export async function verifyZip(source: string): Promise<ZipProblem[]> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_VERIFY, { source });
+}
+
+export interface ZipPackOptions extends ZipWriterOptions {
+  // only packs files matching one of the globs, relative to the source folder
+  include?: string[];
+  // skips files and folders matching one of the globs, like "**/.cache" or "**/*.log"
+  exclude?: string[];
+}
+
+// packs all files of the source folder and returns the sha256 of the created zip
+// if a job id is given, progress is reported and packing can be cancelled with "cancelZipExtraction"
+export async function packDirectoryToZip(
+  source: string,
+  dest: string,
+  jobId?: string,
+  options?: ZipPackOptions,
+): Promise<string> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_PACK_DIRECTORY, {
+    source,
+    dest,
+    jobId,
+    options,
+  });
+}
+
+// WARNING: Do not use directly, only through ZipReader
+export async function loadZipReader(
+  source: string,
+  limits?: ZipLimits,
+): Promise<number> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_READER_LOAD, {
+    source,
+    limits,
+  });
 }
 
 // WARNING: Do not use directly, only through ZipReader
-export async function loadZipReader(source: string): Promise<number> {
-  return invoke(TAURI_COMMAND.ZIP_READER_LOAD, { source });
+export async function loadZipReaderFromEntry(
+  id: number,
+  path: string,
+  limits?: ZipLimits,
+): Promise<number> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_READER_LOAD_FROM_ENTRY, {
+    id,
+    path,
+    limits,
+  });
+}
+
+// WARNING: Do not use directly, only through ZipReader
+export async function loadZipReaderFromBinary(
+  binary: BinaryFileContents,
+  limits?: ZipLimits,
+): Promise<number> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_READER_LOAD_FROM_BINARY, {
+    binary: generateBinaryDataFromContent(binary),
+    limits,
+  });
 }
 
 // WARNING: Do not use directly, only through ZipReader
@@ -139,6 +332,27 @@ export async function getZipReaderEntryNames(
   return invoke(TAURI_COMMAND.ZIP_READER_GET_ENTRY_NAMES, { id, pattern });
 }
 
+// values that are only known for zip archives are null for tar.gz and 7z archives
+export interface ZipEntryMetadata {
+  name: string;
+  compressed_size: number | null;
+  size: number;
+  crc32: number | null;
+  last_modified: string | null; // local time of the archive, without zone
+  compression_method: string | null;
+  is_dir: boolean;
+  is_symlink: boolean;
+  unix_mode: number | null;
+}
+
+// WARNING: Do not use directly, only through ZipReader
+export async function getZipReaderEntryMetadata(
+  id: number,
+  pattern: string = '',
+): Promise<ZipEntryMetadata[]> {
+  return invoke(TAURI_COMMAND.ZIP_READER_GET_ENTRY_METADATA, { id, pattern });
+}
+
 // WARNING: Do not use directly, only through ZipReader
 export async function getZipReaderEntryAsBinary(
   id: number,
@@ -155,9 +369,106 @@ export async function getZipReaderEntryAsText(
   return invoke(TAURI_COMMAND.ZIP_READER_GET_ENTRY_AS_TEXT, { id, path });
 }
 
+// WARNING: Do not use directly, only through ZipReader
+export async function openZipReaderEntryStream(
+  id: number,
+  path: string,
+): Promise<number> {
+  return invoke(TAURI_COMMAND.ZIP_READER_OPEN_ENTRY_STREAM, { id, path });
+}
+
+// WARNING: Do not use directly, only through ZipEntryStream
+// returns null if the entry was fully read
+export async function readZipReaderEntryStream(
+  streamId: number,
+): Promise<Uint8Array | null> {
+  const chunk: string | null = await invoke(
+    TAURI_COMMAND.ZIP_READER_READ_ENTRY_STREAM,
+    { streamId },
+  );
+  return chunk === null
+    ? null
+    : Uint8Array.from(atob(chunk), (char) => char.charCodeAt(0));
+}
+
+// WARNING: Do not use directly, only through ZipEntryStream
+export async function closeZipReaderEntryStream(
+  streamId: number,
+): Promise<void> {
+  return invoke(TAURI_COMMAND.ZIP_READER_CLOSE_ENTRY_STREAM, { streamId });
+}
+
+// WARNING: Do not use directly, only through ZipReader
+export async function extractZipReaderEntryToFile(
+  id: number,
+  path: string,
+  dest: string,
+): Promise<void> {
+  return invoke(TAURI_COMMAND.ZIP_READER_EXTRACT_ENTRY_TO_FILE, {
+    id,
+    path,
+    dest,
+  });
+}
+
+export interface ZipDiffOptions {
+  // adds a unified diff to modified text files
+  textDiff?: boolean;
+  // defaults to ".lua", ".yml", ".yaml" and ".md"
+  textExtensions?: string[];
+  contextLines?: number;
+}
+
+export interface ZipModifiedFile {
+  path: string;
+  base_size: number;
+  size: number;
+  text_diff: string | null;
+}
+
+export interface ZipDiff {
+  added: string[];
+  removed: string[];
+  modified: ZipModifiedFile[];
+}
+
+// WARNING: Do not use directly, only through ZipReader
+export async function diffZipReader(
+  id: number,
+  base: string,
+  options?: ZipDiffOptions,
+): Promise<ZipDiff> {
+  return invokeWithZipSupportError(TAURI_COMMAND.ZIP_READER_DIFF, {
+    id,
+    base,
+    options,
+  });
+}
+
+export interface ZipEntryOptions {
+  compression?: 'stored' | 'deflated';
+  // deflate only, 0 - 9
+  compressionLevel?: number;
+  // only the permission bits, like 0o644
+  unixPermissions?: number;
+  // local time without zone, like "2024-01-31T12:00:00"
+  lastModified?: string;
+}
+
+export interface ZipWriterOptions extends ZipEntryOptions {
+  // fixes timestamps and sorts the entries on close, so the same content results in the same zip
+  reproducible?: boolean;
+  // keeps the entries of an existing zip without recompressing them, written entries replace them
+  // the zip is only replaced on close
+  update?: boolean;
+}
+
 // WARNING: Do not use directly, only through ZipWriter
-export async function loadZipWriter(source: string): Promise<number> {
-  return invoke(TAURI_COMMAND.ZIP_WRITER_LOAD, { source });
+export async function loadZipWriter(
+  source: string,
+  options?: ZipWriterOptions,
+): Promise<number> {
+  return invoke(TAURI_COMMAND.ZIP_WRITER_LOAD, { source, options });
 }
 
 // WARNING: Do not use directly, only through ZipWriter
@@ -169,8 +480,17 @@ export async function closeZipWriter(id: number): Promise<void> {
 export async function addZipWriterDirectory(
   id: number,
   path: string,
+  options?: ZipEntryOptions,
 ): Promise<boolean> {
-  return invoke(TAURI_COMMAND.ZIP_WRITER_ADD_DIRECTORY, { id, path });
+  return invoke(TAURI_COMMAND.ZIP_WRITER_ADD_DIRECTORY, { id, path, options });
+}
+
+// WARNING: Do not use directly, only through ZipWriter
+export async function deleteZipWriterEntry(
+  id: number,
+  path: string,
+): Promise<void> {
+  return invoke(TAURI_COMMAND.ZIP_WRITER_DELETE_ENTRY, { id, path });
 }
 
 // WARNING: Do not use directly, only through ZipWriter
@@ -178,11 +498,13 @@ export async function writeZipWriterEntryFromBinary(
   id: number,
   path: string,
   binary: BinaryFileContents,
+  options?: ZipEntryOptions,
 ): Promise<void> {
   return invoke(TAURI_COMMAND.ZIP_WRITER_WRITE_ENTRY_FROM_BINARY, {
     id,
     path,
     binary: generateBinaryDataFromContent(binary),
+    options,
   });
 }
 
@@ -191,11 +513,13 @@ export async function writeZipWriterEntryFromText(
   id: number,
   path: string,
   text: string,
+  options?: ZipEntryOptions,
 ): Promise<void> {
   return invoke(TAURI_COMMAND.ZIP_WRITER_WRITE_ENTRY_FROM_TEXT, {
     id,
     path,
     text,
+    options,
   });
 }
 
@@ -204,11 +528,13 @@ export async function writeZipWriterEntryFromFile(
   id: number,
   path: string,
   source: string,
+  options?: ZipEntryOptions,
 ): Promise<void> {
   return invoke(TAURI_COMMAND.ZIP_WRITER_WRITE_ENTRY_FROM_FILE, {
     id,
     path,
     source,
+    options,
   });
 }
 
@@ -216,6 +542,28 @@ export async function getSha256OfFile(path: string): Promise<string> {
   return invoke(TAURI_COMMAND.HASH_GET_SHA256_OF_FILE, { path });
 }
 
+export interface ExtensionInfo {
+  is_zip: boolean;
+  // the parsed definition.yml
+  definition: Record<string, unknown>;
+  has_config: boolean;
+  // languages with a file in the locale folder, like "en"
+  locales: string[];
+  // sha256 of the zip, or of all files of a folder
+  hash: string;
+}
+
+export type ExtensionInfoResult =
+  | { status: 'ok'; path: string; info: ExtensionInfo }
+  | { status: 'error'; path: string; message: string };
+
+// reads the basic information of all given extension zips and folders in one call
+export async function getExtensionInfos(
+  paths: string[],
+): Promise<ExtensionInfoResult[]> {
+  return invoke(TAURI_COMMAND.EXTENSIONS_GET_INFOS, { paths });
+}
+
 export async function log(level: number, message: string): Promise<void> {
   return invoke(TAURI_COMMAND.LOGGING_LOG, { level, message });
 }
@@ -228,6 +576,24 @@ export async function osOpenProgram(
   return invoke(TAURI_COMMAND.OS_OPEN_PROGRAM, { path, args, envs });
 }
 
+export interface GameProcess {
+  pid: number;
+  name: string;
+  // null if the process could not be inspected, like elevated processes on windows
+  path: string | null;
+  // seconds since the unix epoch
+  start_time: number;
+  // started through wine or proton
+  wine: boolean;
+}
+
+// lists running game executables of the folder, an empty list means the game is not running
+export async function getRunningGameProcesses(
+  gameFolder: string,
+): Promise<GameProcess[]> {
+  return invoke(TAURI_COMMAND.OS_GET_RUNNING_GAME_PROCESSES, { gameFolder });
+}
+
 // converts a path to once using only slashes
 export async function slashify(path: string): Promise<string> {
   return invoke(TAURI_COMMAND.FILES_SLASHIFY, { path });
@@ -242,14 +608,49 @@ export async function canonicalize(
   return invoke(TAURI_COMMAND.FILES_CANONICALIZE, { path, slash });
 }
 
+export interface ReadDirOptions {
+  // 1 only lists the direct children, no limit by default
+  maxDepth?: number;
+  filesOnly?: boolean;
+  dirsOnly?: boolean;
+  // globs relative to the base, like ".cache/**", matching folders are not entered
+  exclude?: string[];
+  // symlinks are always listed, but only entered if set
+  followSymlinks?: boolean;
+}
+
+export interface DirEntryInfo {
+  path: string;
+  is_dir: boolean;
+  // 0 for folders
+  size: number;
+  // milliseconds since the unix epoch
+  modified: number | null;
+}
+
 // paths returned by this function will always use the slash as separator
 export async function readAndFilterPaths(
   baseDir: string,
   pattern: string = '',
+  options?: ReadDirOptions,
 ): Promise<string[]> {
   return invoke(TAURI_COMMAND.FILES_READ_AND_FILTER_DIR, {
     base: baseDir,
     pattern,
+    options,
+  });
+}
+
+// like "readAndFilterPaths", but every entry carries its size, modification time and type
+export async function readAndFilterDirEntries(
+  baseDir: string,
+  pattern: string = '',
+  options?: ReadDirOptions,
+): Promise<DirEntryInfo[]> {
+  return invoke(TAURI_COMMAND.FILES_READ_AND_FILTER_DIR_ENTRIES, {
+    base: baseDir,
+    pattern,
+    options,
   });
 }
 
@@ -264,3 +665,158 @@ export async function scanFileForBytes(
     scanAmount,
   });
 }
+
+// searches byte signatures like "4D 5A ?? 00" in a single pass, "??" matches any byte
+// returns the offsets per pattern in the same order, at most maxMatches each
+export async function scanFileForPatterns(
+  path: string,
+  patterns: string[],
+  maxMatches?: number,
+  scanAmount?: number,
+): Promise<number[][]> {
+  return invoke(TAURI_COMMAND.FILES_SCAN_FILE_FOR_PATTERNS, {
+    path,
+    patterns,
+    maxMatches,
+    scanAmount,
+  });
+}
+
+export interface PeSection {
+  name: string;
+  virtual_address: number;
+  virtual_size: number;
+  raw_size: number;
+  characteristics: number;
+}
+
+export interface PeVersionInfo {
+  // from the fixed file info, like "1.4.1.0"
+  file_version: string | null;
+  product_version: string | null;
+  product_name: string | null;
+  // windows language id, like 1033 (0x0409) for english
+  language: number | null;
+  code_page: number | null;
+  // all entries of the string table, like "CompanyName"
+  strings: Record<string, string>;
+}
+
+export interface PeInfo {
+  machine: number;
+  is_64_bit: boolean;
+  // seconds since the unix epoch, set by the linker
+  link_timestamp: number;
+  image_size: number;
+  image_base: number;
+  entry_point: number;
+  sections: PeSection[];
+  version_info: PeVersionInfo | null;
+}
+
+// parses the PE headers and the version resource of an executable
+export async function getPeInfo(path: string): Promise<PeInfo> {
+  return invoke(TAURI_COMMAND.FILES_GET_PE_INFO, { path });
+}
+
+// thrown by the safe write commands, the code is usable as localization key:
+// "file.path.not.allowed", "file.backup.missing", "file.locked",
+// "file.not.found", "file.permission.denied" or "file.error"
+export class SafeWriteError extends Error {
+  code: string;
+
+  path: string;
+
+  constructor(code: string, message: string, path: string) {
+    super(message);
+    this.name = 'SafeWriteError';
+    this.code = code;
+    this.path = path;
+  }
+}
+
+async function invokeWithSafeWriteError<T>(
+  cmd: string,
+  args: Record<string, unknown>,
+): Promise<T> {
+  try {
+    return await invoke<T>(cmd, args);
+  } catch (err) {
+    // tauri itself might still fail with plain strings
+    if (typeof err !== 'object' || err === null) {
+      throw err;
+    }
+    const { code, message, path } = err as {
+      code: string;
+      message: string;
+      path: string;
+    };
+    throw new SafeWriteError(code, message, path);
+  }
+}
+
+// the target either keeps its old content or receives the complete new one
+export async function writeFileAtomic(
+  path: string,
+  content: BinaryFileContents | string,
+): Promise<void> {
+  return invokeWithSafeWriteError(TAURI_COMMAND.FILES_WRITE_FILE_ATOMIC, {
+    path,
+    content: generateBinaryDataFromContent(content),
+  });
+}
+
+export async function writeTextFileAtomic(
+  path: string,
+  content: string,
+): Promise<void> {
+  return invokeWithSafeWriteError(TAURI_COMMAND.FILES_WRITE_TEXT_FILE_ATOMIC, {
+    path,
+    content,
+  });
+}
+
+// replaces the target with a copy of the replacement, the old target is restored if this fails
+// returns the path of the backup, if one is kept
+export async function replaceFileSafely(
+  target: string,
+  replacement: string,
+  keepBackup?: boolean,
+): Promise<string | null> {
+  return invokeWithSafeWriteError(TAURI_COMMAND.FILES_REPLACE_FILE_SAFELY, {
+    target,
+    replacement,
+    keepBackup,
+  });
+}
+
+// puts the backup kept by "replaceFileSafely" back in place of the target
+export async function restoreFileBackup(path: string): Promise<void> {
+  return invokeWithSafeWriteError(TAURI_COMMAND.FILES_RESTORE_FILE_BACKUP, {
+    path,
+  });
+}
+
+export interface FsWatchOptions {
+  // only reports paths matching one of the globs, relative to the folder, like "ucp/plugins/**"
+  include?: string[];
+  // ignores paths matching one of the globs, like "**/*.log"
+  exclude?: string[];
+  // only watches the folder itself, not its sub folders
+  nonRecursive?: boolean;
+  // changes are collected until none arrived for this time, 500 by default
+  debounceMs?: number;
+}
+
+// WARNING: Do not use directly, only through "watchFolder" of tauri-files
+// changes are reported to the calling window with the returned id
+export async function watchFolder(
+  path: string,
+  options?: FsWatchOptions,
+): Promise<number> {
+  return invoke(TAURI_COMMAND.FS_WATCHER_WATCH_FOLDER, { path, options });
+}
+
+export async function unwatchFolder(id: number): Promise<void> {
+  return invoke(TAURI_COMMAND.FS_WATCHER_UNWATCH_FOLDER, { id });
+}
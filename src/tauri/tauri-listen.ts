@@ -1,9 +1,11 @@
-/* eslint-disable import/prefer-default-export */
 import { EventCallback, listen } from '@tauri-apps/api/event';
 
 const TAURI_EVENT = {
   LOG_BACKEND: 'backend-log',
   FILE_CONFIG: 'file-config',
+  ZIP_EXTRACT_PROGRESS: 'zip-extract-progress',
+  ZIP_PACK_PROGRESS: 'zip-pack-progress',
+  FS_WATCH: 'fs-watch',
 };
 
 export function onBackendLog(
@@ -17,3 +19,43 @@ export function onGuiFileConfigChange(
 ) {
   return listen(TAURI_EVENT.FILE_CONFIG, func);
 }
+
+export function onZipExtractProgress(
+  func: EventCallback<{
+    job_id: string;
+    entries_done: number;
+    entries_total: number;
+    bytes_written: number;
+    entry: string;
+  }>,
+) {
+  return listen(TAURI_EVENT.ZIP_EXTRACT_PROGRESS, func);
+}
+
+export function onZipPackProgress(
+  func: EventCallback<{
+    job_id: string;
+    files_done: number;
+    files_total: number;
+    bytes_written: number;
+    entry: string;
+  }>,
+) {
+  return listen(TAURI_EVENT.ZIP_PACK_PROGRESS, func);
+}
+
+// paths are absolute and use slashes
+export type FsChange =
+  | { kind: 'created'; path: string }
+  | { kind: 'modified'; path: string }
+  | { kind: 'removed'; path: string }
+  | { kind: 'renamed'; from: string; to: string };
+
+export function onFsWatch(
+  func: EventCallback<{
+    id: number;
+    changes: FsChange[];
+  }>,
+) {
+  return listen(TAURI_EVENT.FS_WATCH, func);
+}
@@ -2,6 +2,8 @@
 import { BinaryFileContents } from '@tauri-apps/api/fs';
 import {
   loadZipReader,
+  loadZipReaderFromEntry,
+  loadZipReaderFromBinary,
   closeZipReader,
   existZipReaderEntry,
   getZipReaderEntryAsBinary,
@@ -9,17 +11,60 @@ import {
   closeZipWriter,
   loadZipWriter,
   addZipWriterDirectory,
+  deleteZipWriterEntry,
   writeZipWriterEntryFromBinary,
   writeZipWriterEntryFromText,
   writeZipWriterEntryFromFile,
   isZipReaderEmpty,
   getZipReaderNumberOfEntries,
   getZipReaderEntryNames,
+  getZipReaderEntryMetadata,
+  ZipEntryOptions,
+  ZipLimits,
+  ZipWriterOptions,
+  openZipReaderEntryStream,
+  readZipReaderEntryStream,
+  closeZipReaderEntryStream,
+  extractZipReaderEntryToFile,
+  diffZipReader,
+  ZipDiffOptions,
 } from '../../tauri/tauri-invoke';
 import Logger from '../scripts/logging';
 
 const LOGGER = new Logger('zip-handler.ts');
 
+export class ZipEntryStream {
+  // do not change, handle like const!
+  static #STREAM_GC_REGISTRY = new FinalizationRegistry((id: number) => {
+    closeZipReaderEntryStream(id).catch((err) =>
+      LOGGER.msg('Error cleaning up not closed zip entry stream:\n{}', err).error(),
+    );
+  });
+
+  #id: number;
+
+  private constructor(id: number) {
+    this.#id = id;
+  }
+
+  static async open(readerId: number, path: string): Promise<ZipEntryStream> {
+    const id = await openZipReaderEntryStream(readerId, path);
+    const stream = new ZipEntryStream(id);
+    ZipEntryStream.#STREAM_GC_REGISTRY.register(stream, id, stream);
+    return stream;
+  }
+
+  async close() {
+    await closeZipReaderEntryStream(this.#id); // will fail if already closed
+    ZipEntryStream.#STREAM_GC_REGISTRY.unregister(this);
+  }
+
+  // returns null if the entry was fully read
+  async readChunk() {
+    return readZipReaderEntryStream(this.#id);
+  }
+}
+
 export class ZipReader {
   // do not change, handle like const!
   static #READER_GC_REGISTRY = new FinalizationRegistry((id: number) => {
@@ -49,13 +94,38 @@ export class ZipReader {
     }
   }
 
-  static async open(path: string): Promise<ZipReader> {
-    const id = await loadZipReader(path);
+  static #register(path: string, id: number) {
     const reader = new ZipReader(path, id);
     ZipReader.#READER_GC_REGISTRY.register(reader, id, reader);
     return reader;
   }
 
+  // archives exceeding the limits are rejected, entries may not be larger than they claim
+  static async open(path: string, limits?: ZipLimits): Promise<ZipReader> {
+    return ZipReader.#register(path, await loadZipReader(path, limits));
+  }
+
+  // the archive is held in memory, the path is only used as name
+  static async fromBinary(
+    path: string,
+    binary: BinaryFileContents,
+    limits?: ZipLimits,
+  ): Promise<ZipReader> {
+    return ZipReader.#register(
+      path,
+      await loadZipReaderFromBinary(binary, limits),
+    );
+  }
+
+  // opens an archive inside this one, like a plugin zip inside an extension pack
+  // stays open if this reader is closed
+  async openEntryAsReader(path: string, limits?: ZipLimits) {
+    return ZipReader.#register(
+      `${this.#path}/${path}`,
+      await loadZipReaderFromEntry(this.#id, path, limits),
+    );
+  }
+
   async close() {
     await closeZipReader(this.#id); // will fail if already closed
     ZipReader.#READER_GC_REGISTRY.unregister(this);
@@ -77,6 +147,10 @@ export class ZipReader {
     return getZipReaderEntryNames(this.#id, globPattern);
   }
 
+  async getEntryMetadata(globPattern?: string) {
+    return getZipReaderEntryMetadata(this.#id, globPattern);
+  }
+
   async getEntryAsBinary(path: string) {
     return getZipReaderEntryAsBinary(this.#id, path);
   }
@@ -84,6 +158,21 @@ export class ZipReader {
   async getEntryAsText(path: string) {
     return getZipReaderEntryAsText(this.#id, path);
   }
+
+  // use for large entries, instead of reading them as binary
+  async openEntryStream(path: string) {
+    return ZipEntryStream.open(this.#id, path);
+  }
+
+  async extractEntryToFile(path: string, dest: string) {
+    return extractZipReaderEntryToFile(this.#id, path, dest);
+  }
+
+  // compares with an archive or folder, like the installed version of a plugin
+  // files only in this reader are reported as added
+  async diff(base: string, options?: ZipDiffOptions) {
+    return diffZipReader(this.#id, base, options);
+  }
 }
 
 export class ZipWriter {
@@ -106,8 +195,9 @@ export class ZipWriter {
   static async withZipWriterDo(
     path: string,
     func: (writer: ZipWriter) => Promise<void>,
+    options?: ZipWriterOptions,
   ): Promise<void> {
-    const writer = await ZipWriter.open(path);
+    const writer = await ZipWriter.open(path, options);
     try {
       await func(writer);
     } finally {
@@ -115,8 +205,12 @@ export class ZipWriter {
     }
   }
 
-  static async open(path: string): Promise<ZipWriter> {
-    const id = await loadZipWriter(path);
+  // options apply to every entry, unless overwritten when writing it
+  static async open(
+    path: string,
+    options?: ZipWriterOptions,
+  ): Promise<ZipWriter> {
+    const id = await loadZipWriter(path, options);
     const writer = new ZipWriter(path, id);
     ZipWriter.#WRITER_GC_REGISTRY.register(writer, id, writer);
     return writer;
@@ -127,19 +221,36 @@ export class ZipWriter {
     ZipWriter.#WRITER_GC_REGISTRY.unregister(this);
   }
 
-  async addDirectory(path: string) {
-    return addZipWriterDirectory(this.#id, path);
+  async addDirectory(path: string, options?: ZipEntryOptions) {
+    return addZipWriterDirectory(this.#id, path, options);
   }
 
-  async writeEntryFromBinary(path: string, binary: BinaryFileContents) {
-    return writeZipWriterEntryFromBinary(this.#id, path, binary);
+  // only for writers opened with "update", a path ending in "/" deletes the whole folder
+  async deleteEntry(path: string) {
+    return deleteZipWriterEntry(this.#id, path);
   }
 
-  async writeEntryFromText(path: string, text: string) {
-    return writeZipWriterEntryFromText(this.#id, path, text);
+  async writeEntryFromBinary(
+    path: string,
+    binary: BinaryFileContents,
+    options?: ZipEntryOptions,
+  ) {
+    return writeZipWriterEntryFromBinary(this.#id, path, binary, options);
   }
 
-  async writeEntryFromFile(path: string, source: string) {
-    return writeZipWriterEntryFromFile(this.#id, path, source);
+  async writeEntryFromText(
+    path: string,
+    text: string,
+    options?: ZipEntryOptions,
+  ) {
+    return writeZipWriterEntryFromText(this.#id, path, text, options);
+  }
+
+  async writeEntryFromFile(
+    path: string,
+    source: string,
+    options?: ZipEntryOptions,
+  ) {
+    return writeZipWriterEntryFromFile(this.#id, path, source, options);
   }
 }